@@ -91,6 +91,18 @@ impl AABB {
         self.start.z <= other.end.z && other.start.z <= self.end.z
     }
 
+    /// Check whether the point is inside the AABB.
+    #[must_use]
+    #[inline]
+    pub fn contains(&self, point: &Point3<f64>) -> bool {
+        self.start.x <= point.x
+            && self.start.y <= point.y
+            && self.start.z <= point.z
+            && point.x <= self.end.x
+            && point.y <= self.end.y
+            && point.z <= self.end.z
+    }
+
     #[must_use]
     #[inline]
     pub const fn start(&self) -> &Point3<f64> {
@@ -0,0 +1,97 @@
+use nalgebra::{UnitQuaternion, Vector3};
+
+use crate::collider::Collider;
+
+/// Air resistance and the lift of spinning spheres, the air is still.
+#[derive(Debug, Clone, Copy)]
+pub struct Aerodynamics {
+    /// Mass per unit volume, in the same units as the material densities.
+    pub air_density: f64,
+    /// Scales the quadratic drag, about 0.5 for a sphere.
+    pub drag_coefficient: f64,
+    /// Scales the Magnus lift of the spinning spheres.
+    pub lift_coefficient: f64,
+}
+
+impl Aerodynamics {
+    /// The density of air at sea level relative to water.
+    pub const AIR_DENSITY: f64 = 0.0012;
+
+    /// The drag force slowing down an object moving through the air.
+    #[must_use]
+    pub fn drag(
+        &self,
+        collider: &Collider,
+        rotation: &UnitQuaternion<f64>,
+        velocity: &Vector3<f64>,
+    ) -> Vector3<f64> {
+        let speed = velocity.magnitude();
+        if speed <= f64::EPSILON {
+            return Vector3::zeros();
+        }
+        let area = collider.cross_section(rotation, &(velocity / speed));
+        -velocity
+            * (0.5 * self.air_density * self.drag_coefficient * area * speed)
+    }
+
+    /// The Magnus force that curves the path of a spinning sphere,
+    /// other colliders get no lift.
+    #[must_use]
+    pub fn lift(
+        &self,
+        collider: &Collider,
+        velocity: &Vector3<f64>,
+        angular_velocity: &Vector3<f64>,
+    ) -> Vector3<f64> {
+        match collider {
+            Collider::Sphere(r) => {
+                angular_velocity.cross(velocity)
+                    * (0.5
+                        * self.air_density
+                        * self.lift_coefficient
+                        * std::f64::consts::PI
+                        * r.powi(3))
+            }
+            Collider::Box(..) | Collider::Compound(_) => Vector3::zeros(),
+        }
+    }
+}
+
+impl Default for Aerodynamics {
+    fn default() -> Self {
+        Self {
+            air_density: Self::AIR_DENSITY,
+            drag_coefficient: 0.5,
+            lift_coefficient: 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topspin_pushes_the_ball_down() {
+        let aerodynamics = Aerodynamics::default();
+        let lift = aerodynamics.lift(
+            &Collider::Sphere(0.5),
+            &Vector3::new(10.0, 0.0, 0.0),
+            &Vector3::new(0.0, 0.0, -30.0),
+        );
+        assert!(lift.y < 0.0);
+        assert!(lift.x.abs() < 1e-12 && lift.z.abs() < 1e-12);
+    }
+
+    #[test]
+    fn drag_opposes_the_velocity() {
+        let aerodynamics = Aerodynamics::default();
+        let velocity = Vector3::new(3.0, -4.0, 0.0);
+        let drag = aerodynamics.drag(
+            &Collider::Box(1.0, 2.0, 3.0),
+            &UnitQuaternion::new(Vector3::new(0.2, 0.4, 0.0)),
+            &velocity,
+        );
+        assert!((drag.normalize() + velocity.normalize()).magnitude() < 1e-12);
+    }
+}
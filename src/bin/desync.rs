@@ -0,0 +1,185 @@
+//! Runs a scenario twice, or compares it with a saved hash stream,
+//! and reports the first step and body where the runs diverge.
+//!
+//! ```text
+//! desync [stack|pile] [--steps N] [--seed S] [--save FILE] [--compare FILE]
+//! ```
+//!
+//! Only the pile is random, the seed is rejected for the stack.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    process::ExitCode,
+};
+
+use nalgebra::Point3;
+use onlab::{
+    collider::Collider,
+    desync::{self, HashStream},
+    object::ObjectBuilder,
+    simulation::Simulation,
+    world::World,
+};
+use rand::Rng;
+
+const DELTA: f64 = 1.0 / 60.0;
+
+#[derive(Debug)]
+struct Options {
+    scenario: String,
+    steps: usize,
+    seed: Option<u64>,
+    save: Option<String>,
+    compare: Option<String>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        scenario: "pile".to_owned(),
+        steps: 600,
+        seed: None,
+        save: None,
+        compare: None,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value =
+            || args.next().ok_or_else(|| format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--steps" => {
+                options.steps = value()?.parse().map_err(|e| format!("{e}"))?;
+            }
+            "--seed" => {
+                options.seed =
+                    Some(value()?.parse().map_err(|e| format!("{e}"))?);
+            }
+            "--save" => options.save = Some(value()?),
+            "--compare" => options.compare = Some(value()?),
+            _ if !arg.starts_with('-') => options.scenario = arg,
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+    if options.scenario == "stack" && options.seed.is_some() {
+        return Err("the stack is not random, it does not take a seed".into());
+    }
+    Ok(options)
+}
+
+/// A stack of boxes, the contacts of the boxes go through GJK.
+/// It is not random, so the seed is not used.
+fn stack(_seed: u64) -> (Simulation, World) {
+    let mut world = World::new();
+    world.insert(
+        ObjectBuilder::new(Collider::Box(100.0, 1.0, 100.0))
+            .immovable()
+            .build(),
+    );
+    for i in 0..10 {
+        world.insert(
+            ObjectBuilder::new(Collider::Box(1.0, 1.0, 1.0))
+                .position(Point3::new(
+                    0.05 * f64::from(i),
+                    1.2f64.mul_add(f64::from(i), 1.0),
+                    0.0,
+                ))
+                .build(),
+        );
+    }
+    (Simulation::default(), world)
+}
+
+/// Random boxes and spheres falling on the ground.
+fn pile(seed: u64) -> (Simulation, World) {
+    let mut simulation = Simulation::default();
+    simulation.reseed(seed);
+    let mut world = World::new();
+    world.insert(
+        ObjectBuilder::new(Collider::Box(100.0, 1.0, 100.0))
+            .immovable()
+            .build(),
+    );
+    let random = simulation.rng();
+    for x in -2..=2 {
+        for y in 1..=5 {
+            for z in -2..=2 {
+                let position = Point3::new(
+                    f64::from(x).mul_add(4.0, random.gen_range(-0.5..=0.5)),
+                    f64::from(y).mul_add(4.0, random.gen_range(-0.5..=0.5)),
+                    f64::from(z).mul_add(4.0, random.gen_range(-0.5..=0.5)),
+                );
+                let collider = if random.gen() {
+                    Collider::Box(
+                        random.gen_range(0.5..=2.0),
+                        random.gen_range(0.5..=2.0),
+                        random.gen_range(0.5..=2.0),
+                    )
+                } else {
+                    Collider::Sphere(random.gen_range(0.25..=1.0))
+                };
+                world.insert(
+                    ObjectBuilder::new(collider).position(position).build(),
+                );
+            }
+        }
+    }
+    (simulation, world)
+}
+
+fn scenario(name: &str) -> Option<fn(u64) -> (Simulation, World)> {
+    match name {
+        "stack" => Some(stack),
+        "pile" => Some(pile),
+        _ => None,
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let Some(scenario) = scenario(&options.scenario) else {
+        eprintln!(
+            "unknown scenario {}, expected stack or pile",
+            options.scenario
+        );
+        return ExitCode::FAILURE;
+    };
+    let seed = options.seed.unwrap_or(0);
+    let hashes = desync::record(|| scenario(seed), options.steps, DELTA);
+    let expected = if let Some(path) = &options.compare {
+        match File::open(path)
+            .and_then(|file| HashStream::read_from(BufReader::new(file)))
+        {
+            Ok(expected) => expected,
+            Err(error) => {
+                eprintln!("cannot read {path}: {error}");
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        desync::record(|| scenario(seed), options.steps, DELTA)
+    };
+    if let Some(path) = &options.save {
+        if let Err(error) = File::create(path)
+            .and_then(|file| hashes.write_to(BufWriter::new(file)))
+        {
+            eprintln!("cannot write {path}: {error}");
+            return ExitCode::FAILURE;
+        }
+    }
+    if let Some(desync) = expected.find_desync(&hashes) {
+        println!("desync: {desync}");
+        return ExitCode::FAILURE;
+    }
+    println!(
+        "no desync in {} steps, final hash {:016x}",
+        hashes.steps().len(),
+        hashes.last().map_or(0, desync::StepHash::hash),
+    );
+    ExitCode::SUCCESS
+}
@@ -1,5 +1,7 @@
 use nalgebra::{Matrix4, Perspective3, Point3, Rotation3, Vector3};
+#[cfg(feature = "ui")]
 use winit::event::{DeviceEvent, Event, KeyEvent, WindowEvent};
+#[cfg(feature = "ui")]
 use winit::keyboard::{Key, NamedKey};
 
 use crate::ray::Ray;
@@ -103,6 +105,7 @@ impl FirstPersonCamera {
         self.focus
     }
 
+    #[cfg(feature = "ui")]
     pub fn event<T>(&mut self, event: &Event<T>) -> bool {
         match event {
             Event::WindowEvent { event, .. } => match event {
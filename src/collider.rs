@@ -1,21 +1,186 @@
+use std::{slice, sync::Arc};
+
 use nalgebra::{
-    Matrix3, Matrix4, Point3, Rotation3, Scale3, Translation3, Vector3, Vector4,
+    Matrix3, Matrix4, Point3, Scale3, Translation3, UnitQuaternion, Vector3,
+    Vector4,
 };
+use smallvec::SmallVec;
 
 use crate::{aabb::AABB, gjk::Support, ray::Ray};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum Collider {
     Sphere(f64),
     Box(f64, f64, f64),
+    /// Shapes glued together, the children are shared between the clones.
+    /// The children should not overlap, the overlapping volume would be
+    /// counted twice in the mass.
+    /// Created with [`Collider::compound`], which rejects compounds
+    /// without any sphere or box.
+    Compound(Arc<[CompoundChild]>),
+}
+
+/// A part of a compound collider, placed relative to the origin of the
+/// compound.
+#[derive(Clone, Debug)]
+pub struct CompoundChild {
+    pub collider: Collider,
+    pub position: Point3<f64>,
+    pub rotation: UnitQuaternion<f64>,
+}
+
+impl CompoundChild {
+    #[must_use]
+    pub const fn new(
+        collider: Collider,
+        position: Point3<f64>,
+        rotation: UnitQuaternion<f64>,
+    ) -> Self {
+        Self {
+            collider,
+            position,
+            rotation,
+        }
+    }
+}
+
+/// A sphere or box part of a collider with its pose relative to the
+/// collider.
+pub type Leaf = (Point3<f64>, UnitQuaternion<f64>, Collider);
+
+/// The iterator of [`Collider::leaves`], it walks the nested compounds
+/// depth first instead of collecting their leaves.
+#[derive(Clone, Debug)]
+pub struct Leaves<'a> {
+    /// A sphere or box is its own only leaf.
+    single: Option<&'a Collider>,
+    stack: SmallVec<[Level<'a>; 4]>,
+}
+
+/// The children left at a level of nesting, with the pose of the compound
+/// they belong to.
+type Level<'a> = (
+    slice::Iter<'a, CompoundChild>,
+    Point3<f64>,
+    UnitQuaternion<f64>,
+);
+
+impl Iterator for Leaves<'_> {
+    type Item = Leaf;
+
+    fn next(&mut self) -> Option<Leaf> {
+        if let Some(leaf) = self.single.take() {
+            return Some((
+                Point3::origin(),
+                UnitQuaternion::identity(),
+                leaf.clone(),
+            ));
+        }
+        while let Some((children, position, rotation)) = self.stack.last_mut() {
+            let Some(child) = children.next() else {
+                self.stack.pop();
+                continue;
+            };
+            let position = *position + *rotation * child.position.coords;
+            let rotation = *rotation * child.rotation;
+            match &child.collider {
+                Collider::Compound(children) => {
+                    self.stack.push((children.iter(), position, rotation));
+                }
+                leaf => return Some((position, rotation, leaf.clone())),
+            }
+        }
+        None
+    }
 }
 
 impl Collider {
+    /// Glues the children together, `None` if there is no sphere or box
+    /// among them, an empty compound would have no mass.
+    #[must_use]
+    pub fn compound(
+        children: impl IntoIterator<Item = CompoundChild>,
+    ) -> Option<Self> {
+        let compound = Self::Compound(children.into_iter().collect());
+        let has_leaves = compound.leaves().next().is_some();
+        has_leaves.then_some(compound)
+    }
+
+    /// The spheres and boxes the collider is made of, the nested compounds
+    /// are flattened while iterating, without allocating.
+    #[must_use]
+    pub fn leaves(&self) -> Leaves<'_> {
+        match self {
+            Self::Sphere(_) | Self::Box(..) => Leaves {
+                single: Some(self),
+                stack: SmallVec::new(),
+            },
+            Self::Compound(children) => Leaves {
+                single: None,
+                stack: smallvec::smallvec![(
+                    children.iter(),
+                    Point3::origin(),
+                    UnitQuaternion::identity(),
+                )],
+            },
+        }
+    }
+
+    /// The center of mass relative to the origin of the collider,
+    /// the density is uniform.
+    #[must_use]
+    pub fn center_of_mass(&self) -> Point3<f64> {
+        match self {
+            Self::Sphere(_) | Self::Box(..) => Point3::origin(),
+            Self::Compound(_) => {
+                let weighted = self
+                    .leaves()
+                    .map(|(position, _, leaf)| position.coords * leaf.volume())
+                    .sum::<Vector3<f64>>();
+                Point3::from(weighted / self.volume())
+            }
+        }
+    }
+
+    /// Moves the children of a compound so its center of mass is at the
+    /// origin, and returns the center of mass before the move.
+    /// Objects rotate around their position, so their colliders have to be
+    /// centered.
+    ///
+    /// # Panics
+    ///
+    /// If the collider is a compound without any sphere or box, those
+    /// are rejected by [`Collider::compound`].
+    #[must_use]
+    pub fn centered(self) -> (Self, Vector3<f64>) {
+        let Self::Compound(children) = &self else {
+            return (self, Vector3::zeros());
+        };
+        assert!(
+            self.leaves().next().is_some(),
+            "a compound collider needs at least one sphere or box"
+        );
+        let center = self.center_of_mass().coords;
+        if center.magnitude_squared() <= f64::EPSILON {
+            return (self, center);
+        }
+        let centered = Self::Compound(
+            children
+                .iter()
+                .map(|child| CompoundChild {
+                    position: child.position - center,
+                    ..child.clone()
+                })
+                .collect(),
+        );
+        (centered, center)
+    }
+
     #[must_use]
     pub fn check_ray_hit(
         &self,
         position: Point3<f64>,
-        rotation: Rotation3<f64>,
+        rotation: UnitQuaternion<f64>,
         ray: &Ray,
     ) -> Option<f64> {
         match self {
@@ -48,12 +213,29 @@ impl Collider {
                     * Scale3::new(*w, *h, *d).to_homogeneous(),
                 ray,
             ),
+            Self::Compound(_) => self
+                .leaves()
+                .filter_map(|(leaf_position, leaf_rotation, leaf)| {
+                    leaf.check_ray_hit(
+                        position + rotation * leaf_position.coords,
+                        rotation * leaf_rotation,
+                        ray,
+                    )
+                })
+                .min_by(f64::total_cmp),
         }
     }
 
     #[must_use]
     #[allow(clippy::missing_panics_doc)]
     pub fn inverse_inertia(&self, mass: f64) -> Matrix3<f64> {
+        self.inertia(mass)
+            .try_inverse()
+            .expect("Inertia tensor should be invertible")
+    }
+
+    /// The inertia tensor around the origin of the collider.
+    fn inertia(&self, mass: f64) -> Matrix3<f64> {
         match &self {
             Self::Sphere(r) => Matrix3::identity() * 2.0 / 3.0 * mass * (r * r),
             #[rustfmt::skip]
@@ -62,16 +244,95 @@ impl Collider {
                 0.0, mass / 12.0 * (d * d + w * w), 0.0,
                 0.0, 0.0, mass / 12.0 * (w * w + h * h)
             ),
+            // the parallel axis theorem moves the inertia of the leaves
+            // from their centers to the origin
+            Self::Compound(_) => {
+                let density = mass / self.volume();
+                self.leaves()
+                    .map(|(position, rotation, leaf)| {
+                        let mass = density * leaf.volume();
+                        let rotation = rotation.to_rotation_matrix();
+                        let offset = position.coords;
+                        rotation * leaf.inertia(mass) * rotation.transpose()
+                            + (Matrix3::identity() * offset.magnitude_squared()
+                                - offset * offset.transpose())
+                                * mass
+                    })
+                    .sum()
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn volume(&self) -> f64 {
+        match self {
+            Self::Sphere(r) => 4.0 / 3.0 * std::f64::consts::PI * r.powi(3),
+            Self::Box(w, h, d) => w * h * d,
+            Self::Compound(children) => {
+                children.iter().map(|child| child.collider.volume()).sum()
+            }
+        }
+    }
+
+    /// The scale of the unit mesh that matches a sphere or a box,
+    /// compounds are drawn by their leaves.
+    #[must_use]
+    pub fn mesh_scale(&self) -> Vector3<f32> {
+        match *self {
+            Self::Sphere(r) => Vector3::new(r, r, r).cast(),
+            Self::Box(w, h, d) => Vector3::new(w, h, d).cast(),
+            Self::Compound(_) => Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    /// The radius of a sphere around the origin that contains the collider,
+    /// it is not the smallest one for compounds.
+    #[must_use]
+    pub fn bounding_radius(&self) -> f64 {
+        match self {
+            Self::Sphere(r) => *r,
+            Self::Box(w, h, d) => Vector3::new(*w, *h, *d).magnitude() / 2.0,
+            Self::Compound(_) => self
+                .leaves()
+                .map(|(position, _, leaf)| {
+                    position.coords.magnitude() + leaf.bounding_radius()
+                })
+                .fold(0.0, f64::max),
+        }
+    }
+
+    /// The area of the shadow of the collider on a plane perpendicular
+    /// to the direction.
+    #[must_use]
+    pub fn cross_section(
+        &self,
+        rotation: &UnitQuaternion<f64>,
+        direction: &Vector3<f64>,
+    ) -> f64 {
+        match self {
+            Self::Sphere(r) => std::f64::consts::PI * r * r,
+            Self::Box(w, h, d) => {
+                let local = rotation.inverse_transform_vector(direction);
+                local.z.abs().mul_add(
+                    w * h,
+                    local.x.abs().mul_add(h * d, local.y.abs() * w * d),
+                )
+            }
+            // overestimated where the leaves shadow each other
+            Self::Compound(_) => self
+                .leaves()
+                .map(|(_, leaf_rotation, leaf)| {
+                    leaf.cross_section(&(rotation * leaf_rotation), direction)
+                })
+                .sum(),
         }
-        .try_inverse()
-        .expect("Inertia tensor should be invertible")
     }
 
     #[must_use]
     pub fn aabb(
         &self,
         position: &Point3<f64>,
-        rotation: &Rotation3<f64>,
+        rotation: &UnitQuaternion<f64>,
     ) -> AABB {
         match self {
             Self::Sphere(r) => AABB::new(
@@ -93,6 +354,16 @@ impl Collider {
                 }
                 AABB::new(min, max)
             }
+            Self::Compound(_) => self
+                .leaves()
+                .map(|(leaf_position, leaf_rotation, leaf)| {
+                    leaf.aabb(
+                        &(position + rotation * leaf_position.coords),
+                        &(rotation * leaf_rotation),
+                    )
+                })
+                .reduce(|a, b| a.merge(&b))
+                .unwrap_or_else(|| AABB::new(*position, *position)),
         }
     }
 }
@@ -160,7 +431,7 @@ const BOX_FACES: [[Point3<f64>; 4]; 6] = [
     ],
 ];
 
-impl Support for (Point3<f64>, Rotation3<f64>, Collider) {
+impl Support for (Point3<f64>, UnitQuaternion<f64>, Collider) {
     fn support(&self, direction: &Vector3<f64>) -> Vector3<f64> {
         let (pos, rot, collider) = self;
         match collider {
@@ -170,6 +441,22 @@ impl Support for (Point3<f64>, Rotation3<f64>, Collider) {
                 let model_pos = 0.5 * model_dir.map(f64::signum);
                 rot * (Scale3::new(*w, *h, *d) * model_pos) + pos.coords
             }
+            // the support of the convex hull of the leaves, the radii of
+            // the spheres are added here because the hull has no radius
+            Collider::Compound(_) => {
+                let unit_direction = direction.normalize();
+                collider
+                    .leaves()
+                    .map(|(position, rotation, leaf)| {
+                        let leaf =
+                            (pos + rot * position.coords, rot * rotation, leaf);
+                        leaf.support(direction) + unit_direction * leaf.radius()
+                    })
+                    .max_by(|a, b| {
+                        a.dot(direction).total_cmp(&b.dot(direction))
+                    })
+                    .unwrap_or(pos.coords)
+            }
         }
     }
 
@@ -177,7 +464,121 @@ impl Support for (Point3<f64>, Rotation3<f64>, Collider) {
         let (_, _, collider) = self;
         match collider {
             Collider::Sphere(r) => *r,
-            Collider::Box(..) => 0.0,
+            Collider::Box(..) | Collider::Compound(_) => 0.0,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two spheres at the ends of a massless rod along the x axis.
+    fn dumbbell() -> Collider {
+        Collider::compound([
+            CompoundChild::new(
+                Collider::Sphere(0.5),
+                Point3::origin(),
+                UnitQuaternion::identity(),
+            ),
+            CompoundChild::new(
+                Collider::Sphere(0.5),
+                Point3::new(2.0, 0.0, 0.0),
+                UnitQuaternion::identity(),
+            ),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn compound_without_parts_is_rejected() {
+        assert!(Collider::compound([]).is_none());
+        let empty = Collider::Compound(Arc::new([]));
+        let nested = CompoundChild::new(
+            empty,
+            Point3::origin(),
+            UnitQuaternion::identity(),
+        );
+        assert!(Collider::compound([nested]).is_none());
+    }
+
+    #[test]
+    fn nested_compounds_are_flattened() {
+        let quarter = UnitQuaternion::from_axis_angle(
+            &Vector3::z_axis(),
+            std::f64::consts::FRAC_PI_2,
+        );
+        let nested = Collider::compound([
+            CompoundChild::new(
+                Collider::Box(1.0, 2.0, 3.0),
+                Point3::origin(),
+                UnitQuaternion::identity(),
+            ),
+            CompoundChild::new(dumbbell(), Point3::new(0.0, 0.0, 1.0), quarter),
+        ])
+        .unwrap();
+        let leaves: Vec<_> = nested.leaves().collect();
+        assert_eq!(leaves.len(), 3);
+        assert!(matches!(leaves[0].2, Collider::Box(..)));
+        // the second sphere of the dumbbell is turned from x to y
+        let (position, rotation, _) = &leaves[2];
+        assert!((position - Point3::new(0.0, 2.0, 1.0)).magnitude() < 1e-12);
+        assert!(rotation.angle_to(&quarter) < 1e-12);
+        assert_eq!(Collider::Sphere(1.0).leaves().count(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "a compound collider needs at least one")]
+    fn empty_compound_cannot_be_centered() {
+        drop(Collider::Compound(Arc::new([])).centered());
+    }
+
+    #[test]
+    fn compound_mass_properties() {
+        let collider = dumbbell();
+        assert!(
+            (collider.center_of_mass() - Point3::new(1.0, 0.0, 0.0))
+                .magnitude()
+                < 1e-12
+        );
+        let (centered, center) = collider.centered();
+        assert!((center - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-12);
+        assert!(centered.center_of_mass().coords.magnitude() < 1e-12);
+        let mass = 4.0;
+        let spheres = 2.0 * Collider::Sphere(0.5).inertia(mass / 2.0)[(0, 0)];
+        let inertia = centered.inverse_inertia(mass).try_inverse().unwrap();
+        // the spheres are one unit away from the y and z axes
+        let expected = Matrix3::from_diagonal(&Vector3::new(
+            spheres,
+            spheres + mass,
+            spheres + mass,
+        ));
+        assert!((inertia - expected).abs().max() < 1e-9);
+    }
+
+    #[test]
+    fn compound_bounds_and_ray_hit() {
+        let (collider, _) = dumbbell().centered();
+        let rotation = UnitQuaternion::from_axis_angle(
+            &Vector3::z_axis(),
+            std::f64::consts::FRAC_PI_2,
+        );
+        let position = Point3::new(0.0, 2.0, 0.0);
+        let aabb = collider.aabb(&position, &rotation);
+        assert!(
+            (aabb.start() - Point3::new(-0.5, 0.5, -0.5)).abs().max() < 1e-9
+        );
+        assert!((aabb.end() - Point3::new(0.5, 3.5, 0.5)).abs().max() < 1e-9);
+        let ray = Ray {
+            start: Point3::origin(),
+            direction: Vector3::y(),
+        };
+        let hit = collider.check_ray_hit(position, rotation, &ray).unwrap();
+        assert!((hit - 0.5).abs() < 1e-9);
+        let miss = Ray {
+            start: Point3::new(0.0, 2.0, -5.0),
+            direction: Vector3::z(),
+        };
+        assert!(collider.check_ray_hit(position, rotation, &miss).is_none());
+    }
+}
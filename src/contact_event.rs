@@ -0,0 +1,66 @@
+use nalgebra::{Point3, Vector3};
+use smallvec::SmallVec;
+
+use crate::{manifold::MAX_MANIFOLD_POINTS, world::BodyHandle};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContactEventKind {
+    /// The objects started touching in this step.
+    Begin,
+    /// The objects were already touching in the previous step.
+    Persist,
+    /// The objects stopped touching in this step.
+    End,
+}
+
+/// A contact between two objects, reported after every step.
+#[derive(Debug, Clone)]
+pub struct ContactEvent {
+    pub kind: ContactEventKind,
+    /// The handles of the objects, the first one is always the smaller.
+    pub bodies: (BodyHandle, BodyHandle),
+    /// Points from the second object towards the first one.
+    /// For [`ContactEventKind::End`] it is the normal of the last contact.
+    /// For compound objects it is the normal of the first touching part.
+    pub normal: Vector3<f64>,
+    /// Empty for [`ContactEventKind::End`].
+    /// Compound objects have the points of every touching part.
+    pub points: SmallVec<[ContactEventPoint; MAX_MANIFOLD_POINTS]>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ContactEventPoint {
+    /// The contact point on the first and on the second object.
+    pub points: (Point3<f64>, Point3<f64>),
+    pub depth: f64,
+    /// The impulse applied along the normal in this step.
+    pub normal_impulse: f64,
+    /// The friction impulse applied to the first object in this step.
+    pub friction_impulse: Vector3<f64>,
+}
+
+impl ContactEvent {
+    /// The sum of the normal impulses of the points,
+    /// useful for deciding how loud or damaging a hit was.
+    #[must_use]
+    pub fn total_normal_impulse(&self) -> f64 {
+        self.points.iter().map(|p| p.normal_impulse).sum()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorEventKind {
+    /// The object started overlapping the sensor in this step.
+    Enter,
+    /// The object stopped overlapping the sensor in this step.
+    Exit,
+}
+
+/// An object entering or leaving a sensor, reported after every step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SensorEvent {
+    pub kind: SensorEventKind,
+    pub sensor: BodyHandle,
+    /// The object overlapping the sensor.
+    pub object: BodyHandle,
+}
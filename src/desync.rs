@@ -0,0 +1,299 @@
+use std::{
+    hash::Hasher,
+    io::{self, Read, Write},
+};
+
+use crate::{
+    object::Object,
+    simulation::Simulation,
+    snapshot::{read_handle, read_u64, write_handle, write_u64},
+    world::{BodyHandle, World},
+};
+
+/// Identifies the hash stream files.
+const MAGIC: &[u8; 8] = b"ONLABHSH";
+/// Increased whenever the layout of the hash stream files changes.
+pub const HASH_STREAM_VERSION: u32 = 1;
+
+/// The 64 bit FNV-1a hash, unlike the standard hasher its results are
+/// the same on every platform and every compiler version, so the hashes
+/// can be saved and compared later.
+#[derive(Debug, Clone, Copy)]
+pub struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// Hashes the state of a body that changes during the simulation,
+/// the floats are hashed bit by bit.
+#[must_use]
+pub fn body_hash(object: &Object) -> u64 {
+    let mut hasher = FnvHasher::default();
+    let (position, rotation) = (object.position(), object.rotation());
+    let floats = position
+        .iter()
+        .chain(rotation.coords.iter())
+        .chain(object.momentum.iter())
+        .chain(object.angular_momentum.iter());
+    for value in floats {
+        hasher.write_u64(value.to_bits());
+    }
+    hasher.write_u8(u8::from(object.sleeping));
+    hasher.finish()
+}
+
+/// The hashes of the bodies after a step, in storage order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepHash {
+    pub bodies: Vec<(BodyHandle, u64)>,
+}
+
+impl StepHash {
+    #[must_use]
+    pub fn of<T>(world: &World<T>) -> Self {
+        Self {
+            bodies: world
+                .iter()
+                .map(|(handle, object)| (handle, body_hash(object)))
+                .collect(),
+        }
+    }
+
+    /// The hash of the whole world.
+    #[must_use]
+    pub fn hash(&self) -> u64 {
+        let mut hasher = FnvHasher::default();
+        for (handle, hash) in &self.bodies {
+            hasher.write_u64(handle.to_bits());
+            hasher.write_u64(*hash);
+        }
+        hasher.finish()
+    }
+}
+
+/// The hashes of the consecutive steps of a run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HashStream {
+    steps: Vec<StepHash>,
+}
+
+/// The first difference between two runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Desync {
+    /// The index of the step in the hash streams.
+    pub step: usize,
+    /// The first body whose state is different, `None` if one of the
+    /// runs has fewer steps.
+    pub body: Option<BodyHandle>,
+}
+
+impl std::fmt::Display for Desync {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.body {
+            Some(body) => write!(f, "step {} diverged at {body:?}", self.step),
+            None => write!(f, "one of the runs ended at step {}", self.step),
+        }
+    }
+}
+
+impl HashStream {
+    pub fn push(&mut self, step: StepHash) {
+        self.steps.push(step);
+    }
+
+    pub fn clear(&mut self) {
+        self.steps.clear();
+    }
+
+    #[must_use]
+    pub fn steps(&self) -> &[StepHash] {
+        &self.steps
+    }
+
+    #[must_use]
+    pub fn last(&self) -> Option<&StepHash> {
+        self.steps.last()
+    }
+
+    /// Finds the first step and body where the other run is different.
+    #[must_use]
+    pub fn find_desync(&self, other: &Self) -> Option<Desync> {
+        for (step, (a, b)) in self.steps.iter().zip(&other.steps).enumerate() {
+            if a == b {
+                continue;
+            }
+            let body = a
+                .bodies
+                .iter()
+                .zip(&b.bodies)
+                .find(|(a, b)| a != b)
+                .map_or_else(
+                    // one of the steps has more bodies
+                    || {
+                        let common = a.bodies.len().min(b.bodies.len());
+                        a.bodies.get(common).or_else(|| b.bodies.get(common))
+                    },
+                    |(a, _)| Some(a),
+                )
+                .map(|(handle, _)| *handle);
+            return Some(Desync { step, body });
+        }
+        (self.steps.len() != other.steps.len()).then(|| Desync {
+            step: self.steps.len().min(other.steps.len()),
+            body: None,
+        })
+    }
+
+    /// Writes the hashes as a versioned little endian file.
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        let w = &mut writer;
+        w.write_all(MAGIC)?;
+        w.write_all(&HASH_STREAM_VERSION.to_le_bytes())?;
+        write_u64(w, self.steps.len() as u64)?;
+        for step in &self.steps {
+            write_u64(w, step.bodies.len() as u64)?;
+            for (handle, hash) in &step.bodies {
+                write_handle(w, *handle)?;
+                write_u64(w, *hash)?;
+            }
+        }
+        w.flush()
+    }
+
+    /// Reads a file written by [`HashStream::write_to`].
+    pub fn read_from(mut reader: impl Read) -> io::Result<Self> {
+        let r = &mut reader;
+        let invalid =
+            |message| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut magic = [0; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a hash stream"));
+        }
+        let mut version = [0; 4];
+        r.read_exact(&mut version)?;
+        if u32::from_le_bytes(version) != HASH_STREAM_VERSION {
+            return Err(invalid("unsupported hash stream version"));
+        }
+        let read_len = |r: &mut _| {
+            usize::try_from(read_u64(r)?)
+                .map_err(|_| invalid("the hash stream is too long"))
+        };
+        let step_count = read_len(r)?;
+        let mut steps = Vec::new();
+        for _ in 0..step_count {
+            let body_count = read_len(r)?;
+            let mut bodies = Vec::new();
+            for _ in 0..body_count {
+                bodies.push((read_handle(r)?, read_u64(r)?));
+            }
+            steps.push(StepHash { bodies });
+        }
+        Ok(Self { steps })
+    }
+}
+
+/// Runs a scenario for the given number of steps, and returns the hashes
+/// of the steps.
+pub fn record<T>(
+    scenario: impl FnOnce() -> (Simulation, World<T>),
+    steps: usize,
+    delta: f64,
+) -> HashStream {
+    let (mut simulation, mut world) = scenario();
+    simulation.hash_states = true;
+    simulation.state_hashes.clear();
+    for _ in 0..steps {
+        simulation.simulate(&mut world, delta);
+    }
+    simulation.state_hashes
+}
+
+/// Runs a scenario twice and returns the first difference between the runs.
+pub fn run_twice<T>(
+    scenario: impl Fn() -> (Simulation, World<T>),
+    steps: usize,
+    delta: f64,
+) -> Option<Desync> {
+    record(&scenario, steps, delta)
+        .find_desync(&record(&scenario, steps, delta))
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Point3, Vector3};
+
+    use super::*;
+    use crate::{collider::Collider, object::ObjectBuilder};
+
+    fn falling_boxes() -> (Simulation, World) {
+        let mut world = World::new();
+        world.insert(
+            ObjectBuilder::new(Collider::Box(100.0, 1.0, 100.0))
+                .immovable()
+                .build(),
+        );
+        for i in 0..5 {
+            world.insert(
+                ObjectBuilder::new(Collider::Box(1.0, 1.0, 1.0))
+                    .position(Point3::new(
+                        0.1 * f64::from(i),
+                        1.2f64.mul_add(f64::from(i), 1.0),
+                        0.0,
+                    ))
+                    .build(),
+            );
+        }
+        (Simulation::default(), world)
+    }
+
+    #[test]
+    fn the_same_scenario_does_not_desync() {
+        assert_eq!(run_twice(falling_boxes, 60, 1.0 / 60.0), None);
+    }
+
+    #[test]
+    fn the_first_diverging_body_is_found() {
+        let expected = record(falling_boxes, 30, 1.0 / 60.0);
+        let (mut simulation, mut world) = falling_boxes();
+        simulation.hash_states = true;
+        let nudged = world.handles()[3];
+        for step in 0..30 {
+            if step == 10 {
+                world[nudged].momentum += Vector3::new(1e-9, 0.0, 0.0);
+            }
+            simulation.simulate(&mut world, 1.0 / 60.0);
+        }
+        assert_eq!(
+            expected.find_desync(&simulation.state_hashes),
+            Some(Desync {
+                step: 10,
+                body: Some(nudged),
+            })
+        );
+    }
+
+    #[test]
+    fn hash_stream_round_trip() {
+        let stream = record(falling_boxes, 10, 1.0 / 60.0);
+        let mut bytes = Vec::new();
+        stream.write_to(&mut bytes).unwrap();
+        assert_eq!(HashStream::read_from(bytes.as_slice()).unwrap(), stream);
+    }
+}
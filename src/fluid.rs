@@ -0,0 +1,528 @@
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+use smallvec::SmallVec;
+
+use crate::{aabb::AABB, collider::Collider};
+
+/// Where the fluid is, the surface is always horizontal.
+#[derive(Debug, Clone)]
+#[allow(variant_size_differences)]
+pub enum FluidRegion {
+    /// Everything below the surface level.
+    HalfSpace { surface_level: f64 },
+    /// A tank with the surface at the top of the box, only the part of an
+    /// object inside the box is submerged.
+    Tank(AABB),
+}
+
+/// A still fluid that pushes the submerged objects up and slows them down.
+#[derive(Debug, Clone)]
+pub struct Fluid {
+    pub region: FluidRegion,
+    /// Mass per unit volume, objects with a smaller density float.
+    pub density: f64,
+    /// The fraction of the velocity lost per second when fully submerged.
+    pub linear_drag: f64,
+    /// The fraction of the angular velocity lost per second when fully
+    /// submerged.
+    pub angular_drag: f64,
+}
+
+/// The part of an object below the surface of a fluid.
+#[derive(Debug, Clone, Copy)]
+pub struct Submersion {
+    pub volume: f64,
+    /// The centroid of the submerged volume, the buoyant force acts here.
+    pub center: Point3<f64>,
+}
+
+impl Fluid {
+    pub const WATER_DENSITY: f64 = 1.0;
+
+    /// The height of the surface if the bounds are in the fluid.
+    #[must_use]
+    pub fn surface_level(&self, bounds: &AABB) -> Option<f64> {
+        match &self.region {
+            FluidRegion::HalfSpace { surface_level } => Some(*surface_level),
+            FluidRegion::Tank(tank) => {
+                tank.overlaps(bounds).then_some(tank.end().y)
+            }
+        }
+    }
+
+    /// Calculates the submerged part of a collider, `None` if it is above
+    /// the surface or outside the tank.
+    #[must_use]
+    pub fn submersion(
+        &self,
+        collider: &Collider,
+        position: &Point3<f64>,
+        rotation: &UnitQuaternion<f64>,
+        bounds: &AABB,
+    ) -> Option<Submersion> {
+        self.surface_level(bounds)?;
+        let submersion =
+            collider_submersion(collider, position, rotation, &self.region);
+        (submersion.volume > f64::EPSILON).then_some(submersion)
+    }
+
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self.region {
+            FluidRegion::HalfSpace { .. } => "Ocean",
+            FluidRegion::Tank(_) => "Tank",
+        }
+    }
+}
+
+impl FluidRegion {
+    /// The corners of the box the fluid fills, a half-space is only
+    /// bounded by its surface.
+    const fn corners(&self) -> (Point3<f64>, Point3<f64>) {
+        match self {
+            Self::HalfSpace { surface_level } => (
+                Point3::new(
+                    f64::NEG_INFINITY,
+                    f64::NEG_INFINITY,
+                    f64::NEG_INFINITY,
+                ),
+                Point3::new(f64::INFINITY, *surface_level, f64::INFINITY),
+            ),
+            Self::Tank(tank) => (*tank.start(), *tank.end()),
+        }
+    }
+
+    /// The planes bounding the fluid as `(normal, offset)` pairs,
+    /// the fluid is where `normal.dot(point) <= offset` for every plane.
+    fn planes(&self) -> SmallVec<[(Vector3<f64>, f64); 6]> {
+        let (min, max) = self.corners();
+        let mut planes = SmallVec::new();
+        for axis in 0..3 {
+            let normal = Vector3::ith(axis, 1.0);
+            if min[axis].is_finite() {
+                planes.push((-normal, -min[axis]));
+            }
+            if max[axis].is_finite() {
+                planes.push((normal, max[axis]));
+            }
+        }
+        planes
+    }
+}
+
+fn collider_submersion(
+    collider: &Collider,
+    position: &Point3<f64>,
+    rotation: &UnitQuaternion<f64>,
+    region: &FluidRegion,
+) -> Submersion {
+    match collider {
+        Collider::Sphere(r) => sphere_submersion(*r, position, region),
+        Collider::Box(w, h, d) => box_submersion(
+            &(Vector3::new(*w, *h, *d) / 2.0),
+            position,
+            rotation,
+            region,
+        ),
+        // the parts are submerged separately, their centers are weighted
+        // by their submerged volumes
+        Collider::Compound(_) => {
+            let mut volume = 0.0;
+            let mut weighted_center = Vector3::zeros();
+            for (leaf_position, leaf_rotation, leaf) in collider.leaves() {
+                let part = collider_submersion(
+                    &leaf,
+                    &(position + rotation * leaf_position.coords),
+                    &(rotation * leaf_rotation),
+                    region,
+                );
+                volume += part.volume;
+                weighted_center += part.center.coords * part.volume;
+            }
+            Submersion {
+                volume,
+                center: if volume > f64::EPSILON {
+                    Point3::from(weighted_center / volume)
+                } else {
+                    *position
+                },
+            }
+        }
+    }
+}
+
+/// The part of the sphere inside the fluid. The pressure on a sphere
+/// points towards its center, so the buoyancy acts right below the center
+/// and only the height of the centroid is calculated.
+fn sphere_submersion(
+    radius: f64,
+    position: &Point3<f64>,
+    region: &FluidRegion,
+) -> Submersion {
+    let (min, max) = region.corners();
+    let cut_by_walls = position.x - radius < min.x
+        || position.x + radius > max.x
+        || position.z - radius < min.z
+        || position.z + radius > max.z;
+    let (volume, moment) = if cut_by_walls {
+        sphere_slices(radius, position, &min, &max)
+    } else {
+        // the layer between the bottom and the surface
+        let (top_volume, top_moment) = sphere_cap(radius, position, max.y);
+        let (bottom_volume, bottom_moment) =
+            sphere_cap(radius, position, min.y);
+        (top_volume - bottom_volume, top_moment - bottom_moment)
+    };
+    Submersion {
+        volume,
+        center: if volume > f64::EPSILON {
+            Point3::new(position.x, moment / volume, position.z)
+        } else {
+            *position
+        },
+    }
+}
+
+/// The volume of the spherical cap below the level and the moment of the
+/// volume around the horizontal plane at zero height, the height of the
+/// cap is measured from the bottom of the sphere.
+fn sphere_cap(radius: f64, position: &Point3<f64>, level: f64) -> (f64, f64) {
+    let height = (level - (position.y - radius)).clamp(0.0, 2.0 * radius);
+    let volume =
+        std::f64::consts::PI * height.powi(2) * 3.0f64.mul_add(radius, -height)
+            / 3.0;
+    let offset = if height > 0.0 {
+        3.0 * 2.0f64.mul_add(radius, -height).powi(2)
+            / (4.0 * 3.0f64.mul_add(radius, -height))
+    } else {
+        0.0
+    };
+    (volume, volume * (position.y - offset))
+}
+
+/// The number of horizontal slices a sphere cut by the walls of a tank is
+/// integrated with.
+const SPHERE_SLICES: u32 = 32;
+
+/// Integrates the areas of the horizontal slices of the sphere inside the
+/// box with Simpson's rule, returns the volume and its moment around the
+/// horizontal plane at zero height.
+fn sphere_slices(
+    radius: f64,
+    position: &Point3<f64>,
+    min: &Point3<f64>,
+    max: &Point3<f64>,
+) -> (f64, f64) {
+    let bottom = (position.y - radius).max(min.y);
+    let top = (position.y + radius).min(max.y);
+    if top <= bottom {
+        return (0.0, 0.0);
+    }
+    let step = (top - bottom) / f64::from(SPHERE_SLICES);
+    let mut volume = 0.0;
+    let mut moment = 0.0;
+    for i in 0..=SPHERE_SLICES {
+        let weight = if i == 0 || i == SPHERE_SLICES {
+            1.0
+        } else if i % 2 == 1 {
+            4.0
+        } else {
+            2.0
+        };
+        let y = step.mul_add(f64::from(i), bottom);
+        let height = y - position.y;
+        let area = disk_rectangle_area(
+            radius.mul_add(radius, -height * height).max(0.0).sqrt(),
+            (min.x - position.x, max.x - position.x),
+            (min.z - position.z, max.z - position.z),
+        );
+        volume += weight * area;
+        moment += weight * area * y;
+    }
+    (volume * step / 3.0, moment * step / 3.0)
+}
+
+/// The area of the disk around the origin inside the rectangle.
+/// The width of the disk inside the rectangle is integrated exactly
+/// between the points where the circle crosses the sides.
+fn disk_rectangle_area(
+    radius: f64,
+    (x0, x1): (f64, f64),
+    (z0, z1): (f64, f64),
+) -> f64 {
+    let start = x0.max(-radius);
+    let end = x1.min(radius);
+    if radius <= 0.0 || end <= start {
+        return 0.0;
+    }
+    // the integral of the half height of the circle
+    let half_circle = |x: f64| {
+        let sine = (x / radius).clamp(-1.0, 1.0);
+        0.5 * x.mul_add(
+            radius.mul_add(radius, -x * x).max(0.0).sqrt(),
+            radius * radius * sine.asin(),
+        )
+    };
+    let mut breaks: SmallVec<[f64; 6]> = smallvec::smallvec![start, end];
+    for z in [z0, z1] {
+        if z.abs() < radius {
+            let x = radius.mul_add(radius, -z * z).sqrt();
+            breaks
+                .extend([-x, x].into_iter().filter(|x| start < *x && *x < end));
+        }
+    }
+    breaks.sort_unstable_by(f64::total_cmp);
+    let mut area = 0.0;
+    for pair in breaks.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let middle = f64::midpoint(a, b);
+        let half_height =
+            radius.mul_add(radius, -middle * middle).max(0.0).sqrt();
+        if z1.min(half_height) <= z0.max(-half_height) {
+            continue;
+        }
+        let top = if z1 < half_height {
+            z1 * (b - a)
+        } else {
+            half_circle(b) - half_circle(a)
+        };
+        let bottom = if z0 > -half_height {
+            z0 * (b - a)
+        } else {
+            half_circle(a) - half_circle(b)
+        };
+        area += top - bottom;
+    }
+    area
+}
+
+/// Clips the box with the planes of the fluid, and sums the tetrahedra
+/// formed by the faces of the clipped box and its center.
+fn box_submersion(
+    half_extents: &Vector3<f64>,
+    position: &Point3<f64>,
+    rotation: &UnitQuaternion<f64>,
+    region: &FluidRegion,
+) -> Submersion {
+    let corner = |x: f64, y: f64, z: f64| {
+        position
+            + rotation
+                * Vector3::new(
+                    x * half_extents.x,
+                    y * half_extents.y,
+                    z * half_extents.z,
+                )
+    };
+    let corners = [
+        corner(-1.0, -1.0, -1.0),
+        corner(1.0, -1.0, -1.0),
+        corner(1.0, 1.0, -1.0),
+        corner(-1.0, 1.0, -1.0),
+        corner(-1.0, -1.0, 1.0),
+        corner(1.0, -1.0, 1.0),
+        corner(1.0, 1.0, 1.0),
+        corner(-1.0, 1.0, 1.0),
+    ];
+    // counter-clockwise when looking at the face from outside
+    let mut faces: Vec<Polygon> = [
+        [0, 3, 2, 1],
+        [4, 5, 6, 7],
+        [0, 1, 5, 4],
+        [3, 7, 6, 2],
+        [0, 4, 7, 3],
+        [1, 2, 6, 5],
+    ]
+    .iter()
+    .map(|face| face.iter().map(|&i| corners[i]).collect())
+    .collect();
+    for (normal, offset) in region.planes() {
+        faces = clip_polyhedron(&faces, &normal, offset);
+    }
+    let mut volume = 0.0;
+    let mut weighted_center = Vector3::zeros();
+    for polygon in &faces {
+        for i in 1..polygon.len().saturating_sub(1) {
+            let (a, b, c) = (polygon[0], polygon[i], polygon[i + 1]);
+            let tetrahedron = (a - position)
+                .dot(&(b - position).cross(&(c - position)))
+                / 6.0;
+            volume += tetrahedron;
+            weighted_center +=
+                (position.coords + a.coords + b.coords + c.coords)
+                    * (tetrahedron / 4.0);
+        }
+    }
+    // a box touching the fluid with a face gets a flat, inside out volume
+    let volume = volume.max(0.0);
+    Submersion {
+        volume,
+        center: if volume > f64::EPSILON {
+            Point3::from(weighted_center / volume)
+        } else {
+            *position
+        },
+    }
+}
+
+type Polygon = SmallVec<[Point3<f64>; 8]>;
+
+/// The part of a convex polyhedron behind the plane, the hole left by the
+/// plane is closed with a new face.
+fn clip_polyhedron(
+    faces: &[Polygon],
+    normal: &Vector3<f64>,
+    offset: f64,
+) -> Vec<Polygon> {
+    let mut clipped = Vec::with_capacity(faces.len() + 1);
+    let mut cut = Polygon::new();
+    for face in faces {
+        // a face on the plane is replaced by the new face
+        if face.iter().all(|p| offset - normal.dot(&p.coords) == 0.0) {
+            cut.extend_from_slice(face);
+            continue;
+        }
+        let polygon = clip_polygon(face, normal, offset, &mut cut);
+        if polygon.len() >= 3 {
+            clipped.push(polygon);
+        }
+    }
+    if cut.len() >= 3 {
+        // the points of the cut are ordered counter-clockwise around the
+        // normal, so the new face looks outwards
+        let center = cut.iter().map(|p| p.coords).sum::<Vector3<f64>>()
+            / cut.len() as f64;
+        let u = normal
+            .cross(&Vector3::x())
+            .try_normalize(1e-6)
+            .unwrap_or_else(|| normal.cross(&Vector3::y()).normalize());
+        let v = normal.cross(&u);
+        let angle = |p: &Point3<f64>| {
+            let d = p.coords - center;
+            d.dot(&v).atan2(d.dot(&u))
+        };
+        cut.sort_unstable_by(|a, b| angle(a).total_cmp(&angle(b)));
+        clipped.push(cut);
+    }
+    clipped
+}
+
+/// The part of a convex polygon behind the plane, the points on the plane
+/// are added to `cut`.
+fn clip_polygon(
+    polygon: &[Point3<f64>],
+    normal: &Vector3<f64>,
+    offset: f64,
+    cut: &mut Polygon,
+) -> Polygon {
+    let mut clipped = Polygon::new();
+    for (i, &current) in polygon.iter().enumerate() {
+        let next = polygon[(i + 1) % polygon.len()];
+        let current_depth = offset - normal.dot(&current.coords);
+        let next_depth = offset - normal.dot(&next.coords);
+        if current_depth >= 0.0 {
+            clipped.push(current);
+            if current_depth == 0.0 {
+                cut.push(current);
+            }
+        }
+        if (current_depth >= 0.0) != (next_depth >= 0.0) {
+            let t = current_depth / (current_depth - next_depth);
+            let point = current + (next - current) * t;
+            clipped.push(point);
+            cut.push(point);
+        }
+    }
+    clipped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ocean(surface_level: f64) -> Fluid {
+        Fluid {
+            region: FluidRegion::HalfSpace { surface_level },
+            density: Fluid::WATER_DENSITY,
+            linear_drag: 0.0,
+            angular_drag: 0.0,
+        }
+    }
+
+    #[test]
+    fn half_submerged_box() {
+        let collider = Collider::Box(2.0, 2.0, 2.0);
+        let rotation = UnitQuaternion::new(Vector3::new(0.0, 0.3, 0.0));
+        let position = Point3::origin();
+        let bounds = collider.aabb(&position, &rotation);
+        let submersion = ocean(0.0)
+            .submersion(&collider, &position, &rotation, &bounds)
+            .unwrap();
+        assert!((submersion.volume - 4.0).abs() < 1e-9);
+        assert!((submersion.center.y + 0.5).abs() < 1e-9);
+    }
+
+    fn tank(start: Point3<f64>, end: Point3<f64>) -> Fluid {
+        Fluid {
+            region: FluidRegion::Tank(AABB::new(start, end)),
+            ..ocean(0.0)
+        }
+    }
+
+    #[test]
+    fn box_half_outside_the_tank_wall() {
+        let fluid = tank(
+            Point3::new(-10.0, -10.0, -10.0),
+            Point3::new(10.0, 0.0, 10.0),
+        );
+        let collider = Collider::Box(2.0, 2.0, 2.0);
+        let rotation = UnitQuaternion::identity();
+        let position = Point3::new(10.0, -5.0, 0.0);
+        let bounds = collider.aabb(&position, &rotation);
+        let submersion = fluid
+            .submersion(&collider, &position, &rotation, &bounds)
+            .unwrap();
+        assert!((submersion.volume - 4.0).abs() < 1e-9);
+        assert!(
+            (submersion.center - Point3::new(9.5, -5.0, 0.0)).magnitude()
+                < 1e-9
+        );
+    }
+
+    #[test]
+    fn sphere_half_outside_the_tank_wall() {
+        let fluid = tank(
+            Point3::new(-10.0, -10.0, -10.0),
+            Point3::new(10.0, 0.0, 10.0),
+        );
+        let collider = Collider::Sphere(1.0);
+        let rotation = UnitQuaternion::identity();
+        let position = Point3::new(0.0, -5.0, 10.0);
+        let bounds = collider.aabb(&position, &rotation);
+        let submersion = fluid
+            .submersion(&collider, &position, &rotation, &bounds)
+            .unwrap();
+        // the slices are integrated numerically
+        assert!((submersion.volume - collider.volume() / 2.0).abs() < 1e-6);
+        assert!((submersion.center - position).magnitude() < 1e-9);
+        // the bottom of the tank cuts off the lower half
+        let below = Point3::new(0.0, -10.0, 0.0);
+        let bounds = collider.aabb(&below, &rotation);
+        let submersion = fluid
+            .submersion(&collider, &below, &rotation, &bounds)
+            .unwrap();
+        assert!((submersion.volume - collider.volume() / 2.0).abs() < 1e-9);
+        assert!(submersion.center.y > -10.0);
+    }
+
+    #[test]
+    fn fully_submerged_sphere() {
+        let collider = Collider::Sphere(1.0);
+        let rotation = UnitQuaternion::identity();
+        let position = Point3::new(0.0, -5.0, 0.0);
+        let bounds = collider.aabb(&position, &rotation);
+        let submersion = ocean(0.0)
+            .submersion(&collider, &position, &rotation, &bounds)
+            .unwrap();
+        assert!((submersion.volume - collider.volume()).abs() < 1e-9);
+        assert!((submersion.center - position).magnitude() < 1e-9);
+    }
+}
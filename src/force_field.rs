@@ -0,0 +1,161 @@
+use nalgebra::{Point3, Vector3};
+
+use crate::aabb::AABB;
+
+/// A field of external forces.
+///
+/// `Uniform` and `Wind` apply the same force to every object regardless of
+/// its mass, so heavy objects are pushed less than light ones, unlike by
+/// gravity. `PointAttractor` and `Vortex` accelerate every object the same,
+/// their force is scaled by the mass of the object.
+#[derive(Debug, Clone)]
+pub enum ForceField {
+    /// A constant force in newtons applied to every object.
+    Uniform { force: Vector3<f64> },
+    /// Pulls objects towards `center` with an acceleration of
+    /// `strength / r^2`, closer than `min_distance` the acceleration
+    /// does not grow further. A negative strength pushes objects away.
+    PointAttractor {
+        center: Point3<f64>,
+        strength: f64,
+        min_distance: f64,
+    },
+    /// A constant force in newtons applied to the objects whose center is
+    /// inside the volume.
+    Wind { volume: AABB, force: Vector3<f64> },
+    /// Swirls objects around the axis going through `center`, the tangential
+    /// acceleration is `strength` at the axis and falls off linearly
+    /// until `radius`.
+    Vortex {
+        center: Point3<f64>,
+        axis: Vector3<f64>,
+        strength: f64,
+        radius: f64,
+    },
+}
+
+impl ForceField {
+    /// The force exerted on an object with the given position and mass.
+    #[must_use]
+    pub fn force_at(&self, position: &Point3<f64>, mass: f64) -> Vector3<f64> {
+        match self {
+            Self::Uniform { force } => *force,
+            Self::PointAttractor {
+                center,
+                strength,
+                min_distance,
+            } => {
+                let offset = center - position;
+                let distance = offset.magnitude().max(*min_distance);
+                offset.try_normalize(f64::EPSILON).unwrap_or_default()
+                    * (strength * mass / distance.powi(2))
+            }
+            Self::Wind { volume, force } => {
+                if volume.contains(position) {
+                    *force
+                } else {
+                    Vector3::zeros()
+                }
+            }
+            Self::Vortex {
+                center,
+                axis,
+                strength,
+                radius,
+            } => {
+                let Some(axis) = axis.try_normalize(f64::EPSILON) else {
+                    return Vector3::zeros();
+                };
+                let offset = position - center;
+                let radial = offset - axis * axis.dot(&offset);
+                let distance = radial.magnitude();
+                if distance >= *radius || distance <= f64::EPSILON {
+                    return Vector3::zeros();
+                }
+                axis.cross(&radial).normalize()
+                    * (strength * mass * (1.0 - distance / radius))
+            }
+        }
+    }
+
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Uniform { .. } => "Uniform",
+            Self::PointAttractor { .. } => "Point attractor",
+            Self::Wind { .. } => "Wind",
+            Self::Vortex { .. } => "Vortex",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniform_force_does_not_depend_on_the_mass() {
+        let field = ForceField::Uniform {
+            force: Vector3::new(1.0, 2.0, 3.0),
+        };
+        let position = Point3::new(5.0, -3.0, 1.0);
+        assert_eq!(field.force_at(&position, 1.0), Vector3::new(1.0, 2.0, 3.0));
+        assert_eq!(
+            field.force_at(&position, 10.0),
+            Vector3::new(1.0, 2.0, 3.0)
+        );
+    }
+
+    #[test]
+    fn attractor_is_clamped_at_the_min_distance() {
+        let field = ForceField::PointAttractor {
+            center: Point3::origin(),
+            strength: 8.0,
+            min_distance: 1.0,
+        };
+        let far = field.force_at(&Point3::new(2.0, 0.0, 0.0), 3.0);
+        assert!((far - Vector3::new(-6.0, 0.0, 0.0)).magnitude() < 1e-12);
+        let near = field.force_at(&Point3::new(0.5, 0.0, 0.0), 3.0);
+        assert!((near - Vector3::new(-24.0, 0.0, 0.0)).magnitude() < 1e-12);
+        assert_eq!(field.force_at(&Point3::origin(), 3.0), Vector3::zeros());
+    }
+
+    #[test]
+    fn wind_only_blows_inside_the_volume() {
+        let field = ForceField::Wind {
+            volume: AABB::new(
+                Point3::new(-1.0, -1.0, -1.0),
+                Point3::new(1.0, 1.0, 1.0),
+            ),
+            force: Vector3::new(0.0, 0.0, 5.0),
+        };
+        assert_eq!(
+            field.force_at(&Point3::new(0.5, 0.0, 0.0), 2.0),
+            Vector3::new(0.0, 0.0, 5.0)
+        );
+        assert_eq!(
+            field.force_at(&Point3::new(1.5, 0.0, 0.0), 2.0),
+            Vector3::zeros()
+        );
+    }
+
+    #[test]
+    fn vortex_falls_off_until_the_radius() {
+        let field = ForceField::Vortex {
+            center: Point3::origin(),
+            axis: Vector3::y(),
+            strength: 4.0,
+            radius: 2.0,
+        };
+        let half = field.force_at(&Point3::new(1.0, 3.0, 0.0), 2.0);
+        assert!((half - Vector3::new(0.0, 0.0, -4.0)).magnitude() < 1e-12);
+        assert_eq!(
+            field.force_at(&Point3::new(2.0, 0.0, 0.0), 2.0),
+            Vector3::zeros()
+        );
+        assert_eq!(
+            field.force_at(&Point3::new(0.0, 1.0, 0.0), 2.0),
+            Vector3::zeros()
+        );
+    }
+}
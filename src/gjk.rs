@@ -1,7 +1,6 @@
 use std::ops::{Add, Mul, Sub};
 
 use nalgebra::{Const, DimMin, Matrix, Vector3};
-use rand::random;
 use smallvec::SmallVec;
 
 type SimplexData = SmallVec<[SupportPoint; 4]>;
@@ -13,6 +12,10 @@ const TOLERANCE: f64 = 1e-7;
 const SIMPLEX_MAX_DIM: usize = 4;
 const EPA_MAX_ITER: usize = 10;
 const GJK_MAX_ITER: usize = 12;
+/// The first search direction, it is not parallel to the axes, so the
+/// first support point of an axis aligned box is not ambiguous.
+/// A fixed direction keeps the results reproducible.
+const INITIAL_DIRECTION: Vec3 = Vec3::new(0.6, 0.48, 0.64);
 
 pub trait Support {
     fn support(&self, direction: &Vec3) -> Vec3;
@@ -35,11 +38,7 @@ impl SupportPoint {
 
 pub fn gjk(a: &impl Support, b: &impl Support) -> GJKResult {
     let mut s = SimplexData::with_capacity(4);
-    s.push(SupportPoint::new(
-        a,
-        b,
-        &Vec3::new(random(), random(), random()),
-    ));
+    s.push(SupportPoint::new(a, b, &INITIAL_DIRECTION));
     let mut prev_dist = f64::INFINITY;
     let mut closest_point = closest_simplex(&mut s);
     let mut dist_diff = 0.0;
@@ -50,6 +49,14 @@ pub fn gjk(a: &impl Support, b: &impl Support) -> GJKResult {
         if s.len() == SIMPLEX_MAX_DIM {
             return epa(a, b, s.into_vec());
         }
+        // the origin is on the simplex, so the shapes touch or overlap,
+        // but the closest point has no direction to continue the search in
+        if dist <= TOLERANCE {
+            if !expand_simplex(a, b, &mut s) {
+                return GJKResult::NoContact;
+            }
+            return epa(a, b, s.into_vec());
+        }
         debug_assert!(
             dist <= prev_dist + TOLERANCE,
             "prev_dist={prev_dist}, dist={dist}",
@@ -77,6 +84,88 @@ pub fn gjk(a: &impl Support, b: &impl Support) -> GJKResult {
     closest_point_to_contact(a, b, &closest_point)
 }
 
+/// Adds support points to the simplex until it is a tetrahedron, so EPA
+/// can start from it. Returns false if the Minkowski difference is flat.
+fn expand_simplex(
+    a: &impl Support,
+    b: &impl Support,
+    s: &mut SimplexData,
+) -> bool {
+    let axes = [Vec3::x(), Vec3::y(), Vec3::z()];
+    while s.len() < SIMPLEX_MAX_DIM {
+        let directions: SmallVec<[Vec3; 6]> = match s.len() {
+            1 => axes.iter().flat_map(|axis| [*axis, -axis]).collect(),
+            2 => {
+                let edge = s[1].diff - s[0].diff;
+                axes.iter()
+                    .map(|axis| edge.cross(axis))
+                    .flat_map(|direction| [direction, -direction])
+                    .collect()
+            }
+            _ => {
+                let normal =
+                    (s[1].diff - s[0].diff).cross(&(s[2].diff - s[0].diff));
+                smallvec::smallvec![normal, -normal]
+            }
+        };
+        let Some(new_point) = directions
+            .iter()
+            .map(|direction| SupportPoint::new(a, b, direction))
+            .find(|point| distance_from_simplex(s, point) > TOLERANCE)
+        else {
+            return false;
+        };
+        s.push(new_point);
+    }
+    true
+}
+
+/// The distance of the point from the line or plane going through
+/// the points of the simplex.
+fn distance_from_simplex(s: &SimplexData, point: &SupportPoint) -> f64 {
+    let offset = point.diff - s[0].diff;
+    match s.len() {
+        1 => offset.magnitude(),
+        2 => {
+            let edge = s[1].diff - s[0].diff;
+            offset.cross(&edge).magnitude() / edge.magnitude()
+        }
+        _ => {
+            let normal =
+                (s[1].diff - s[0].diff).cross(&(s[2].diff - s[0].diff));
+            offset.dot(&normal).abs() / normal.magnitude()
+        }
+    }
+}
+
+/// The vector from the closest point of `b` to the closest point of `a`,
+/// ignoring the radii of the shapes. Returns `None` if the shapes
+/// (without the radii) overlap.
+pub fn closest_difference(a: &impl Support, b: &impl Support) -> Option<Vec3> {
+    let mut s = SimplexData::with_capacity(4);
+    s.push(SupportPoint::new(a, b, &Vec3::x()));
+    let mut closest_point = closest_simplex(&mut s);
+    for _ in 0..GJK_MAX_ITER {
+        if s.len() == SIMPLEX_MAX_DIM {
+            return None;
+        }
+        if closest_point.diff.magnitude() <= TOLERANCE {
+            return Some(closest_point.diff);
+        }
+        let new_point = SupportPoint::new(a, b, &-closest_point.diff);
+        if closest_point
+            .diff
+            .dot(&(new_point.diff - closest_point.diff))
+            >= -TOLERANCE
+        {
+            return Some(closest_point.diff);
+        }
+        s.push(new_point);
+        closest_point = closest_simplex(&mut s);
+    }
+    Some(closest_point.diff)
+}
+
 #[allow(clippy::similar_names)]
 #[allow(clippy::too_many_lines)]
 fn best_simplex(s: &mut SimplexData) {
@@ -404,6 +493,7 @@ fn closest_point_to_contact(
                 b_point + normal * b.radius() / (a.radius() + b.radius()),
             ),
             normal: normal.normalize(),
+            depth: a.radius() + b.radius() - normal.magnitude(),
         }
     } else {
         GJKResult::NoContact
@@ -476,36 +566,37 @@ pub fn epa(
 ) -> GJKResult {
     debug_assert_eq!(points.len(), 4);
     let mut faces = vec![[0, 1, 2], [0, 2, 3], [0, 3, 1], [1, 2, 3]];
+    // the polytope only grows, so the center of the first tetrahedron
+    // stays inside it, the normals of the faces point away from it
+    let inside = points.iter().map(|p| p.diff).sum::<Vec3>() / 4.0;
     let mut closest_points = vec![];
+    let mut planes = vec![];
     let mut tmp = SimplexData::new();
-    for [v1, v2, v3] in &faces {
-        tmp.push(points[*v1]);
-        tmp.push(points[*v2]);
-        tmp.push(points[*v3]);
+    for face in &faces {
+        for v in face {
+            tmp.push(points[*v]);
+        }
         let closest_point = closest_simplex(&mut tmp);
         tmp.clear();
         closest_points.push(closest_point);
+        planes.push(face_plane(&points, *face, &inside));
     }
     let mut iter = 0;
     loop {
-        let Some(minface) = closest_points
+        let Some(minface) = planes
             .iter()
             .enumerate()
-            .min_by(|(_, a), (_, b)| {
-                a.diff.magnitude().total_cmp(&b.diff.magnitude())
-            })
+            .min_by(|(_, (_, a)), (_, (_, b))| a.total_cmp(b))
             .map(|(i, _)| i)
         else {
             eprintln!("math has failed!");
             return GJKResult::NoContact;
         };
-        let new_point = SupportPoint::new(a, b, &closest_points[minface].diff);
-        debug_assert!(
-            new_point.diff.magnitude()
-                >= closest_points[minface].diff.magnitude() - TOLERANCE,
-        );
-        if new_point.diff.dot(&closest_points[minface].diff)
-            <= closest_points[minface].diff.magnitude_squared() + TOLERANCE
+        // the origin can be on the closest face, the normal of the face
+        // is the search direction even then
+        let (normal, distance) = planes[minface];
+        let new_point = SupportPoint::new(a, b, &normal);
+        if new_point.diff.dot(&normal) <= distance + TOLERANCE
             || iter == EPA_MAX_ITER
             || points.iter().any(|p| p.diff == new_point.diff)
         {
@@ -516,23 +607,22 @@ pub fn epa(
                 closest_points[minface].a - closest_points[minface].diff;
             return GJKResult::Contact {
                 points: (closest_points[minface].a, b_point),
-                normal: -closest_points[minface].diff.normalize(),
+                normal: -normal,
+                depth: distance + a.radius() + b.radius(),
             };
         }
         let mut edges = vec![];
         let mut i = 0;
         debug_assert_eq!(faces.len(), closest_points.len());
         while i < faces.len() {
-            if closest_points[i]
-                .diff
-                .dot(&(new_point.diff - closest_points[i].diff))
-                >= 0.0
-            {
+            let (normal, _) = planes[i];
+            if normal.dot(&(new_point.diff - points[faces[i][0]].diff)) > 0.0 {
                 edges.add_or_remove(minmax(faces[i][0], faces[i][1]));
                 edges.add_or_remove(minmax(faces[i][1], faces[i][2]));
                 edges.add_or_remove(minmax(faces[i][2], faces[i][0]));
                 faces.swap_remove(i);
                 closest_points.swap_remove(i);
+                planes.swap_remove(i);
             } else {
                 i += 1;
             }
@@ -544,24 +634,52 @@ pub fn epa(
             new_faces.push([i, j, points.len()]);
         }
         points.push(new_point);
-        let mut new_closest_points = vec![];
-        for [v1, v2, v3] in &new_faces {
-            tmp.push(points[*v1]);
-            tmp.push(points[*v2]);
-            tmp.push(points[*v3]);
+        for face in &new_faces {
+            for v in face {
+                tmp.push(points[*v]);
+            }
             let closest_point = closest_simplex(&mut tmp);
             tmp.clear();
-            new_closest_points.push(closest_point);
+            closest_points.push(closest_point);
+            planes.push(face_plane(&points, *face, &inside));
         }
         faces.append(&mut new_faces);
-        closest_points.append(&mut new_closest_points);
         iter += 1;
     }
 }
 
+/// The unit normal of the face pointing away from `inside`, and the
+/// distance of its plane from the origin. Faces without area are never
+/// the closest ones.
+fn face_plane(
+    points: &[SupportPoint],
+    [v1, v2, v3]: [usize; 3],
+    inside: &Vec3,
+) -> (Vec3, f64) {
+    let p1 = points[v1].diff;
+    let Some(normal) = (points[v2].diff - p1)
+        .cross(&(points[v3].diff - p1))
+        .try_normalize(f64::EPSILON)
+    else {
+        return (Vec3::zeros(), f64::INFINITY);
+    };
+    let normal = if normal.dot(&(p1 - inside)) < 0.0 {
+        -normal
+    } else {
+        normal
+    };
+    (normal, normal.dot(&p1))
+}
+
 #[derive(Debug)]
+#[allow(variant_size_differences)]
 pub enum GJKResult {
-    Contact { points: (Vec3, Vec3), normal: Vec3 },
+    Contact {
+        points: (Vec3, Vec3),
+        normal: Vec3,
+        /// The penetration depth along the normal.
+        depth: f64,
+    },
     UnknownContact(Vec<SupportPoint>),
     NoContact,
 }
@@ -957,4 +1075,29 @@ mod tests {
         best_simplex(&mut s);
         assert_eq!(expected, s);
     }
+
+    #[test]
+    fn centered_box_on_a_wide_box_overlaps() {
+        use nalgebra::{Point3, UnitQuaternion};
+
+        use crate::collider::Collider;
+
+        // the origin lies on the first triangle of the simplex
+        let floor = (
+            Point3::new(0.0, -0.5, 0.0),
+            UnitQuaternion::identity(),
+            Collider::Box(100.0, 1.0, 100.0),
+        );
+        let cube = (
+            Point3::new(0.0, 0.3, 0.0),
+            UnitQuaternion::identity(),
+            Collider::Box(1.0, 1.0, 1.0),
+        );
+        let GJKResult::Contact { normal, depth, .. } = gjk(&floor, &cube)
+        else {
+            panic!("the boxes overlap");
+        };
+        assert!((normal - -Vec3::y()).magnitude() < 1e-9);
+        assert!((depth - 0.2).abs() < 1e-9);
+    }
 }
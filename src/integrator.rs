@@ -0,0 +1,26 @@
+/// How the simulation advances the objects in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Integrator {
+    /// Updates the momentum first, then moves the object with it.
+    SymplecticEuler,
+    /// Applies half of the force before and half of it after moving.
+    VelocityVerlet,
+    /// The classic fourth order Runge-Kutta method, the change of the
+    /// inertia during the rotation is also integrated.
+    RungeKutta4,
+    /// Symplectic Euler with the gyroscopic torque solved implicitly,
+    /// which keeps fast spinning objects stable.
+    ImplicitGyroscopic,
+}
+
+impl Integrator {
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::SymplecticEuler => "Symplectic Euler",
+            Self::VelocityVerlet => "Velocity Verlet",
+            Self::RungeKutta4 => "RK4",
+            Self::ImplicitGyroscopic => "Implicit gyroscopic",
+        }
+    }
+}
@@ -0,0 +1,655 @@
+use nalgebra::{Matrix3, Point3, UnitQuaternion, Vector3};
+
+use crate::{
+    object::Object,
+    world::{BodyHandle, World},
+};
+
+#[derive(Debug, Clone)]
+pub enum JointKind {
+    /// Keeps the anchors together, the bodies can rotate freely.
+    BallSocket,
+    /// Keeps the anchors together and only allows rotation around the axis.
+    Hinge {
+        /// The hinge axis in the space of the first body.
+        axis_a: Vector3<f64>,
+        /// The hinge axis in the space of the second body.
+        axis_b: Vector3<f64>,
+        /// A vector perpendicular to the axis in the space of the first body,
+        /// the angle of the hinge is measured between the references.
+        reference_a: Vector3<f64>,
+        /// The same vector in the space of the second body.
+        reference_b: Vector3<f64>,
+        /// The minimum and maximum angle of the hinge in radians.
+        limits: Option<(f64, f64)>,
+    },
+    /// Keeps the distance of the anchors between `min` and `max`,
+    /// a rope has a minimum distance of zero.
+    Distance { min: f64, max: f64 },
+    /// Keeps the anchors together and the relative rotation of the bodies.
+    Fixed {
+        /// The rotation of the first body relative to the second body.
+        relative_rotation: UnitQuaternion<f64>,
+    },
+}
+
+/// Connects two objects, or an object and the world.
+///
+/// The anchors are stored in the space of their body, when the joint is
+/// attached to the world the second anchor is in world space.
+#[derive(Debug, Clone)]
+pub struct Joint {
+    pub body_a: BodyHandle,
+    /// `None` attaches the joint to the world.
+    pub body_b: Option<BodyHandle>,
+    pub anchor_a: Point3<f64>,
+    pub anchor_b: Point3<f64>,
+    pub kind: JointKind,
+    limit_impulse: f64,
+    distance_impulse: f64,
+}
+
+impl Joint {
+    /// Returns `None` if both ends of the joint are the same body,
+    /// a body cannot be connected to itself.
+    #[must_use]
+    pub fn new(
+        body_a: BodyHandle,
+        anchor_a: Point3<f64>,
+        body_b: Option<BodyHandle>,
+        anchor_b: Point3<f64>,
+        kind: JointKind,
+    ) -> Option<Self> {
+        (Some(body_a) != body_b).then_some(Self {
+            body_a,
+            body_b,
+            anchor_a,
+            anchor_b,
+            kind,
+            limit_impulse: 0.0,
+            distance_impulse: 0.0,
+        })
+    }
+
+    /// Creates a ball-socket joint at the world space anchor.
+    #[must_use]
+    pub fn ball_socket<T>(
+        world: &World<T>,
+        body_a: BodyHandle,
+        body_b: Option<BodyHandle>,
+        anchor: Point3<f64>,
+    ) -> Option<Self> {
+        Self::new(
+            body_a,
+            world[body_a].local_point(&anchor),
+            body_b,
+            local_point(world, body_b, &anchor),
+            JointKind::BallSocket,
+        )
+    }
+
+    /// Creates a hinge joint at the world space anchor
+    /// rotating around the world space axis.
+    #[must_use]
+    pub fn hinge<T>(
+        world: &World<T>,
+        body_a: BodyHandle,
+        body_b: Option<BodyHandle>,
+        anchor: Point3<f64>,
+        axis: Vector3<f64>,
+        limits: Option<(f64, f64)>,
+    ) -> Option<Self> {
+        let axis = axis.normalize();
+        let reference = perpendicular(&axis);
+        let rotation_a = world[body_a].rotation().inverse();
+        let rotation_b = rotation(world, body_b).inverse();
+        Self::new(
+            body_a,
+            world[body_a].local_point(&anchor),
+            body_b,
+            local_point(world, body_b, &anchor),
+            JointKind::Hinge {
+                axis_a: rotation_a * axis,
+                axis_b: rotation_b * axis,
+                reference_a: rotation_a * reference,
+                reference_b: rotation_b * reference,
+                limits,
+            },
+        )
+    }
+
+    /// Creates a joint that keeps the distance of the world space anchors
+    /// between `min` and `max`.
+    #[must_use]
+    pub fn distance<T>(
+        world: &World<T>,
+        body_a: BodyHandle,
+        body_b: Option<BodyHandle>,
+        anchor_a: Point3<f64>,
+        anchor_b: Point3<f64>,
+        min: f64,
+        max: f64,
+    ) -> Option<Self> {
+        Self::new(
+            body_a,
+            world[body_a].local_point(&anchor_a),
+            body_b,
+            local_point(world, body_b, &anchor_b),
+            JointKind::Distance { min, max },
+        )
+    }
+
+    /// Creates a rope between the world space anchors,
+    /// the length of the rope is the current distance of the anchors.
+    #[must_use]
+    pub fn rope<T>(
+        world: &World<T>,
+        body_a: BodyHandle,
+        body_b: Option<BodyHandle>,
+        anchor_a: Point3<f64>,
+        anchor_b: Point3<f64>,
+    ) -> Option<Self> {
+        let length = (anchor_a - anchor_b).magnitude();
+        Self::distance(world, body_a, body_b, anchor_a, anchor_b, 0.0, length)
+    }
+
+    /// Creates a joint that welds the bodies together at the world space
+    /// anchor.
+    #[must_use]
+    pub fn fixed<T>(
+        world: &World<T>,
+        body_a: BodyHandle,
+        body_b: Option<BodyHandle>,
+        anchor: Point3<f64>,
+    ) -> Option<Self> {
+        Self::new(
+            body_a,
+            world[body_a].local_point(&anchor),
+            body_b,
+            local_point(world, body_b, &anchor),
+            JointKind::Fixed {
+                relative_rotation: rotation(world, body_b).inverse()
+                    * world[body_a].rotation(),
+            },
+        )
+    }
+
+    /// The anchors in world space.
+    #[must_use]
+    pub fn world_anchors<T>(
+        &self,
+        world: &World<T>,
+    ) -> (Point3<f64>, Point3<f64>) {
+        (
+            world[self.body_a].world_point(&self.anchor_a),
+            self.body_b.map_or(self.anchor_b, |b| {
+                world[b].world_point(&self.anchor_b)
+            }),
+        )
+    }
+
+    /// Whether the connected bodies are still in the world.
+    #[must_use]
+    pub fn bodies_exist<T>(&self, world: &World<T>) -> bool {
+        world.contains(self.body_a)
+            && self.body_b.is_none_or(|b| world.contains(b))
+    }
+
+    /// Whether the simulation moves any of the connected objects.
+    #[must_use]
+    pub fn is_active<T>(&self, world: &World<T>) -> bool {
+        world[self.body_a].is_moving()
+            || self.body_b.is_some_and(|b| world[b].is_moving())
+    }
+
+    pub(crate) const fn prepare(&mut self) {
+        self.limit_impulse = 0.0;
+        self.distance_impulse = 0.0;
+    }
+
+    /// Applies the impulses that keep the joint together for one iteration
+    /// of the solver. The position error is corrected with a Baumgarte
+    /// bias, `bias_factor` is the correction factor divided by the step,
+    /// so that fraction of the error is corrected in every step.
+    pub(crate) fn solve<T>(&mut self, world: &mut World<T>, bias_factor: f64) {
+        let (anchor_a, anchor_b) = self.world_anchors(world);
+        let (a, mut b) = bodies_mut(world, self.body_a, self.body_b);
+        match self.kind {
+            JointKind::BallSocket => {
+                solve_point(a, b, anchor_a, anchor_b, bias_factor);
+            }
+            JointKind::Hinge {
+                axis_a,
+                axis_b,
+                reference_a,
+                reference_b,
+                limits,
+            } => {
+                solve_point(
+                    a,
+                    b.as_deref_mut(),
+                    anchor_a,
+                    anchor_b,
+                    bias_factor,
+                );
+                solve_hinge(
+                    a,
+                    b,
+                    (axis_a, axis_b),
+                    (reference_a, reference_b),
+                    limits,
+                    bias_factor,
+                    &mut self.limit_impulse,
+                );
+            }
+            JointKind::Distance { min, max } => {
+                solve_distance(
+                    a,
+                    b,
+                    (anchor_a, anchor_b),
+                    (min, max),
+                    bias_factor,
+                    &mut self.distance_impulse,
+                );
+            }
+            JointKind::Fixed { relative_rotation } => {
+                solve_point(
+                    a,
+                    b.as_deref_mut(),
+                    anchor_a,
+                    anchor_b,
+                    bias_factor,
+                );
+                solve_rotation(a, b, &relative_rotation, bias_factor);
+            }
+        }
+    }
+}
+
+/// Keeps the hinge axes aligned and the angle of the hinge within the limits.
+fn solve_hinge(
+    a: &mut Object,
+    mut b: Option<&mut Object>,
+    (axis_a, axis_b): (Vector3<f64>, Vector3<f64>),
+    (reference_a, reference_b): (Vector3<f64>, Vector3<f64>),
+    limits: Option<(f64, f64)>,
+    bias_factor: f64,
+    limit_impulse: &mut f64,
+) {
+    let rotation_b = b
+        .as_deref()
+        .map_or_else(UnitQuaternion::identity, Object::rotation);
+    let axis_a = a.rotation() * axis_a;
+    let axis_b = rotation_b * axis_b;
+    // rotating the first body around this aligns the axes
+    let error = axis_a.cross(&axis_b);
+    let tangent1 = perpendicular(&axis_a);
+    let tangent2 = axis_a.cross(&tangent1);
+    for tangent in [tangent1, tangent2] {
+        solve_angular(
+            a,
+            b.as_deref_mut(),
+            &tangent,
+            bias_factor * tangent.dot(&error),
+            &mut 0.0,
+            (f64::NEG_INFINITY, f64::INFINITY),
+        );
+    }
+    if let Some((min, max)) = limits {
+        let reference_a = a.rotation() * reference_a;
+        let reference_b = rotation_b * reference_b;
+        let angle = reference_b
+            .cross(&reference_a)
+            .dot(&axis_a)
+            .atan2(reference_b.dot(&reference_a));
+        let (target, range) = if angle <= min {
+            (bias_factor * (min - angle), (0.0, f64::INFINITY))
+        } else if angle >= max {
+            (bias_factor * (max - angle), (f64::NEG_INFINITY, 0.0))
+        } else {
+            return;
+        };
+        solve_angular(a, b, &axis_a, target, limit_impulse, range);
+    }
+}
+
+/// Keeps the distance of the world space anchors within the range.
+fn solve_distance(
+    a: &mut Object,
+    b: Option<&mut Object>,
+    (anchor_a, anchor_b): (Point3<f64>, Point3<f64>),
+    (min, max): (f64, f64),
+    bias_factor: f64,
+    distance_impulse: &mut f64,
+) {
+    let offset = anchor_a - anchor_b;
+    let length = offset.magnitude();
+    let Some(normal) = offset.try_normalize(f64::EPSILON) else {
+        return;
+    };
+    let (target, range) = if min >= max {
+        (
+            -bias_factor * (length - max),
+            (f64::NEG_INFINITY, f64::INFINITY),
+        )
+    } else if length >= max {
+        (-bias_factor * (length - max), (f64::NEG_INFINITY, 0.0))
+    } else if length <= min {
+        (-bias_factor * (length - min), (0.0, f64::INFINITY))
+    } else {
+        return;
+    };
+    let effectiveness = a.impulse_effectiveness(anchor_a, normal)
+        + b.as_deref()
+            .map_or(0.0, |b| b.impulse_effectiveness(anchor_b, normal));
+    if effectiveness <= f64::EPSILON {
+        return;
+    }
+    let normal_velocity = normal.dot(
+        &(a.local_velocity(anchor_a) - velocity_at(b.as_deref(), anchor_b)),
+    );
+    let impulse_strength = accumulate(
+        distance_impulse,
+        (target - normal_velocity) / effectiveness,
+        range,
+    );
+    a.apply_impulse(anchor_a, normal * impulse_strength);
+    if let Some(b) = b {
+        b.apply_impulse(anchor_b, -normal * impulse_strength);
+    }
+}
+
+/// Keeps the rotation of the first body relative to the second one.
+fn solve_rotation(
+    a: &mut Object,
+    b: Option<&mut Object>,
+    relative_rotation: &UnitQuaternion<f64>,
+    bias_factor: f64,
+) {
+    let rotation_b = b
+        .as_deref()
+        .map_or_else(UnitQuaternion::identity, Object::rotation);
+    let error = (a.rotation() * (rotation_b * relative_rotation).inverse())
+        .scaled_axis();
+    let inverse_inertia =
+        a.effective_inverse_inertia() + inverse_inertia(b.as_deref());
+    let Some(effective_inertia) = inverse_inertia.try_inverse() else {
+        return;
+    };
+    let relative_angular_velocity =
+        a.angular_velocity() - angular_velocity(b.as_deref());
+    let impulse =
+        effective_inertia * (-error * bias_factor - relative_angular_velocity);
+    a.apply_angular_impulse(impulse);
+    if let Some(b) = b {
+        b.apply_angular_impulse(-impulse);
+    }
+}
+
+/// Keeps the two world space anchors together.
+fn solve_point(
+    a: &mut Object,
+    b: Option<&mut Object>,
+    anchor_a: Point3<f64>,
+    anchor_b: Point3<f64>,
+    bias_factor: f64,
+) {
+    let offset_a = (anchor_a - a.position()).cross_matrix();
+    let offset_b = (anchor_b
+        - b.as_deref().map_or_else(Point3::origin, Object::position))
+    .cross_matrix();
+    let inverse_mass = Matrix3::identity()
+        * (a.effective_inverse_mass()
+            + b.as_deref().map_or(0.0, Object::effective_inverse_mass))
+        - offset_a * a.effective_inverse_inertia() * offset_a
+        - offset_b * inverse_inertia(b.as_deref()) * offset_b;
+    let Some(effective_mass) = inverse_mass.try_inverse() else {
+        return;
+    };
+    let relative_velocity =
+        a.local_velocity(anchor_a) - velocity_at(b.as_deref(), anchor_b);
+    let impulse = effective_mass
+        * (-(anchor_a - anchor_b) * bias_factor - relative_velocity);
+    a.apply_impulse(anchor_a, impulse);
+    if let Some(b) = b {
+        b.apply_impulse(anchor_b, -impulse);
+    }
+}
+
+/// Drives the relative angular velocity around the axis towards the target.
+fn solve_angular(
+    a: &mut Object,
+    b: Option<&mut Object>,
+    axis: &Vector3<f64>,
+    target: f64,
+    accumulated_impulse: &mut f64,
+    range: (f64, f64),
+) {
+    let effectiveness = axis.dot(
+        &((a.effective_inverse_inertia() + inverse_inertia(b.as_deref()))
+            * axis),
+    );
+    if effectiveness <= f64::EPSILON {
+        return;
+    }
+    let angular_velocity =
+        axis.dot(&(a.angular_velocity() - angular_velocity(b.as_deref())));
+    let impulse_strength = accumulate(
+        accumulated_impulse,
+        (target - angular_velocity) / effectiveness,
+        range,
+    );
+    a.apply_angular_impulse(axis * impulse_strength);
+    if let Some(b) = b {
+        b.apply_angular_impulse(-axis * impulse_strength);
+    }
+}
+
+/// Adds the impulse to the accumulated impulse, clamps it to the range,
+/// and returns the change that has to be applied.
+fn accumulate(accumulated: &mut f64, impulse: f64, range: (f64, f64)) -> f64 {
+    let new_accumulated = (*accumulated + impulse).clamp(range.0, range.1);
+    let change = new_accumulated - *accumulated;
+    *accumulated = new_accumulated;
+    change
+}
+
+fn velocity_at(body: Option<&Object>, point: Point3<f64>) -> Vector3<f64> {
+    body.map_or_else(Vector3::zeros, |b| b.local_velocity(point))
+}
+
+fn angular_velocity(body: Option<&Object>) -> Vector3<f64> {
+    body.map_or_else(Vector3::zeros, Object::angular_velocity)
+}
+
+fn inverse_inertia(body: Option<&Object>) -> Matrix3<f64> {
+    body.map_or_else(Matrix3::zeros, Object::effective_inverse_inertia)
+}
+
+fn rotation<T>(
+    world: &World<T>,
+    body: Option<BodyHandle>,
+) -> UnitQuaternion<f64> {
+    body.map_or_else(UnitQuaternion::identity, |b| world[b].rotation())
+}
+
+fn local_point<T>(
+    world: &World<T>,
+    body: Option<BodyHandle>,
+    world_point: &Point3<f64>,
+) -> Point3<f64> {
+    body.map_or(*world_point, |b| world[b].local_point(world_point))
+}
+
+/// An arbitrary unit vector perpendicular to the given one.
+fn perpendicular(v: &Vector3<f64>) -> Vector3<f64> {
+    let other = if v.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+    v.cross(&other).normalize()
+}
+
+/// Mutably borrows the first body and the optional second body.
+fn bodies_mut<T>(
+    world: &mut World<T>,
+    a: BodyHandle,
+    b: Option<BodyHandle>,
+) -> (&mut Object, Option<&mut Object>) {
+    match b {
+        None => (&mut world[a], None),
+        Some(b) => {
+            let (a, b) = world.pair_mut(a, b);
+            (a, Some(b))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collider::Collider, object::ObjectBuilder, simulation::Simulation,
+    };
+
+    fn simulate_steps(
+        simulation: &mut Simulation,
+        world: &mut World<()>,
+        steps: u32,
+        mut check: impl FnMut(&World<()>),
+    ) {
+        for _ in 0..steps {
+            simulation.simulate(world, 1.0 / 60.0);
+            check(world);
+        }
+    }
+
+    #[test]
+    fn joint_to_the_same_body_is_rejected() {
+        let mut world = World::<()>::new();
+        let body =
+            world.insert(ObjectBuilder::new(Collider::Sphere(1.0)).build());
+        assert!(
+            Joint::ball_socket(&world, body, Some(body), Point3::origin())
+                .is_none()
+        );
+        assert!(
+            Joint::ball_socket(&world, body, None, Point3::origin()).is_some()
+        );
+    }
+
+    #[test]
+    fn hinge_angle_is_clamped_to_the_limits() {
+        let mut world = World::<()>::new();
+        let body = world.insert(
+            ObjectBuilder::new(Collider::Sphere(0.2))
+                .position(Point3::new(1.0, 0.0, 0.0))
+                .build(),
+        );
+        let mut simulation = Simulation::default();
+        simulation.joints.extend(Joint::hinge(
+            &world,
+            body,
+            None,
+            Point3::origin(),
+            Vector3::z(),
+            Some((-0.5, 0.5)),
+        ));
+        // gravity swings the body down, rotating it around -z
+        simulate_steps(&mut simulation, &mut world, 300, |world| {
+            let rotation = world[body].rotation();
+            let angle = rotation.scaled_axis().z;
+            assert!(angle > -0.55, "{angle}");
+            assert!(rotation.angle() - angle.abs() < 0.01);
+        });
+        let angle = world[body].rotation().scaled_axis().z;
+        assert!((angle + 0.5).abs() < 0.05, "{angle}");
+    }
+
+    #[test]
+    fn distance_joint_holds_its_length() {
+        let mut world = World::<()>::new();
+        let body = world.insert(
+            ObjectBuilder::new(Collider::Sphere(0.2))
+                .position(Point3::new(0.0, -2.0, 0.0))
+                .velocity(Vector3::new(3.0, 0.0, 1.0))
+                .build(),
+        );
+        let mut simulation = Simulation::default();
+        simulation.joints.extend(Joint::distance(
+            &world,
+            body,
+            None,
+            Point3::new(0.0, -2.0, 0.0),
+            Point3::origin(),
+            2.0,
+            2.0,
+        ));
+        simulate_steps(&mut simulation, &mut world, 300, |world| {
+            let length = world[body].position().coords.magnitude();
+            assert!((length - 2.0).abs() < 0.05, "{length}");
+        });
+    }
+
+    #[test]
+    fn rope_only_limits_the_maximum_length() {
+        let mut world = World::<()>::new();
+        let body = world.insert(
+            ObjectBuilder::new(Collider::Sphere(0.2))
+                .position(Point3::new(0.0, -2.0, 0.0))
+                .velocity(Vector3::new(0.0, 4.0, 0.0))
+                .build(),
+        );
+        let mut simulation = Simulation::default();
+        simulation.joints.extend(Joint::rope(
+            &world,
+            body,
+            None,
+            Point3::new(0.0, -2.0, 0.0),
+            Point3::origin(),
+        ));
+        let mut shortest = f64::INFINITY;
+        simulate_steps(&mut simulation, &mut world, 300, |world| {
+            let length = world[body].position().coords.magnitude();
+            assert!(length < 2.05, "{length}");
+            shortest = shortest.min(length);
+        });
+        // the rope went slack while the body was thrown upwards
+        assert!(shortest < 1.5, "{shortest}");
+    }
+
+    #[test]
+    fn fixed_joint_keeps_the_relative_pose() {
+        let mut world = World::<()>::new();
+        let a = world.insert(ObjectBuilder::new(Collider::Sphere(0.5)).build());
+        let b = world.insert(
+            ObjectBuilder::new(Collider::Box(1.0, 0.5, 0.5))
+                .position(Point3::new(1.5, 0.0, 0.0))
+                .build(),
+        );
+        let mut simulation = Simulation::default();
+        simulation.gravity = Vector3::zeros();
+        simulation.joints.extend(Joint::fixed(
+            &world,
+            a,
+            Some(b),
+            Point3::new(0.75, 0.0, 0.0),
+        ));
+        world[b].apply_impulse(
+            Point3::new(2.0, 0.0, 0.0),
+            Vector3::new(0.0, 2.0, 1.0),
+        );
+        // the impulse is only spread to the other body by the next solve,
+        // the error of the first steps is corrected by the bias
+        simulate_steps(&mut simulation, &mut world, 30, |_| {});
+        simulate_steps(&mut simulation, &mut world, 270, |world| {
+            let local = world[a].local_point(&world[b].position());
+            let error = (local - Point3::new(1.5, 0.0, 0.0)).magnitude();
+            assert!(error < 0.05, "{error}");
+            let relative = world[a].rotation().inverse() * world[b].rotation();
+            assert!(relative.angle() < 0.01, "{}", relative.angle());
+        });
+        assert!(world[a].angular_velocity().magnitude() > 1.0);
+    }
+}
@@ -0,0 +1,171 @@
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+
+/// Decides how a kinematic object moves, it can be changed by user code
+/// between the steps.
+#[derive(Debug)]
+pub enum KinematicMotion {
+    /// Moves with constant velocities.
+    Velocity {
+        linear: Vector3<f64>,
+        angular: Vector3<f64>,
+    },
+    /// Reaches the target pose at the end of the next step.
+    Target {
+        position: Point3<f64>,
+        rotation: UnitQuaternion<f64>,
+    },
+    /// Follows the path, the pose is reached at the given time.
+    Path(KinematicPath),
+}
+
+/// A pose as a function of the elapsed time.
+pub struct KinematicPath(Box<PathFn>);
+
+type PathFn = dyn Fn(f64) -> (Point3<f64>, UnitQuaternion<f64>) + Send + Sync;
+
+impl KinematicPath {
+    pub fn new(
+        path: impl Fn(f64) -> (Point3<f64>, UnitQuaternion<f64>)
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        Self(Box::new(path))
+    }
+
+    /// Interpolates between the `(time, position, rotation)` keyframes,
+    /// the keyframes have to be ordered by time.
+    /// A looping path starts over after the last keyframe,
+    /// otherwise it stops there.
+    ///
+    /// # Panics
+    /// If there are no keyframes.
+    #[must_use]
+    pub fn keyframes(
+        keyframes: Vec<(f64, Point3<f64>, UnitQuaternion<f64>)>,
+        looping: bool,
+    ) -> Self {
+        let &(end, end_position, end_rotation) = keyframes
+            .last()
+            .expect("a path needs at least one keyframe");
+        Self::new(move |time| {
+            let time = if looping && end > 0.0 {
+                time.rem_euclid(end)
+            } else {
+                time
+            };
+            let next = keyframes.partition_point(|(t, ..)| *t <= time);
+            if next == 0 {
+                let (_, position, rotation) = keyframes[0];
+                return (position, rotation);
+            }
+            let Some(&(t2, p2, r2)) = keyframes.get(next) else {
+                return (end_position, end_rotation);
+            };
+            let (t1, p1, r1) = keyframes[next - 1];
+            let t = (time - t1) / (t2 - t1);
+            (p1 + (p2 - p1) * t, r1.slerp(&r2, t))
+        })
+    }
+
+    #[must_use]
+    pub fn pose_at(&self, time: f64) -> (Point3<f64>, UnitQuaternion<f64>) {
+        (self.0)(time)
+    }
+}
+
+impl std::fmt::Debug for KinematicPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("KinematicPath").finish_non_exhaustive()
+    }
+}
+
+/// The state of an object that is moved by user code instead of forces.
+/// Kinematic objects push other objects as if they had infinite mass.
+#[derive(Debug)]
+pub struct Kinematic {
+    pub motion: KinematicMotion,
+    /// The time elapsed on the path.
+    pub time: f64,
+    velocity: Vector3<f64>,
+    angular_velocity: Vector3<f64>,
+}
+
+impl Kinematic {
+    #[must_use]
+    pub const fn new(motion: KinematicMotion) -> Self {
+        Self {
+            motion,
+            time: 0.0,
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+            angular_velocity: Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    /// The velocity in the current step.
+    #[must_use]
+    pub const fn velocity(&self) -> &Vector3<f64> {
+        &self.velocity
+    }
+
+    /// The angular velocity in the current step.
+    #[must_use]
+    pub const fn angular_velocity(&self) -> &Vector3<f64> {
+        &self.angular_velocity
+    }
+
+    /// Calculates the velocities that move the object from its current pose
+    /// along the motion in the next step.
+    pub(crate) fn prepare(
+        &mut self,
+        position: &Point3<f64>,
+        rotation: &UnitQuaternion<f64>,
+        delta: f64,
+    ) {
+        let (target_position, target_rotation) = match &self.motion {
+            KinematicMotion::Velocity { linear, angular } => {
+                self.velocity = *linear;
+                self.angular_velocity = *angular;
+                return;
+            }
+            KinematicMotion::Target { position, rotation } => {
+                (*position, *rotation)
+            }
+            KinematicMotion::Path(path) => {
+                self.time += delta;
+                path.pose_at(self.time)
+            }
+        };
+        self.velocity = (target_position - position) / delta;
+        self.angular_velocity =
+            (target_rotation * rotation.inverse()).scaled_axis() / delta;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyframes_are_interpolated_and_looped() {
+        let path = KinematicPath::keyframes(
+            vec![
+                (0.0, Point3::origin(), UnitQuaternion::identity()),
+                (2.0, Point3::new(4.0, 0.0, 0.0), UnitQuaternion::identity()),
+            ],
+            true,
+        );
+        assert!((path.pose_at(0.5).0.x - 1.0).abs() < 1e-9);
+        assert!((path.pose_at(2.5).0.x - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn target_is_reached_in_one_step() {
+        let mut kinematic = Kinematic::new(KinematicMotion::Target {
+            position: Point3::new(1.0, 2.0, 3.0),
+            rotation: UnitQuaternion::identity(),
+        });
+        kinematic.prepare(&Point3::origin(), &UnitQuaternion::identity(), 0.5);
+        assert_eq!(kinematic.velocity(), &Vector3::new(2.0, 4.0, 6.0));
+    }
+}
@@ -1,19 +1,44 @@
 pub mod aabb;
+pub mod aerodynamics;
+#[cfg(feature = "render")]
 pub mod camera;
+pub mod ccd;
 pub mod collider;
+pub mod contact_event;
+#[cfg(feature = "ui")]
 pub mod context;
+pub mod desync;
+pub mod fluid;
+pub mod force_field;
 pub mod gjk;
+pub mod integrator;
+pub mod joint;
+pub mod kinematic;
+#[cfg(feature = "render")]
 pub mod light;
+#[cfg(feature = "ui")]
 pub mod main_scene;
+pub mod manifold;
+pub mod material;
+#[cfg(feature = "render")]
 pub mod mesh;
+#[cfg(feature = "render")]
 pub mod meshes;
 pub mod object;
 pub mod ray;
 pub mod recording;
+#[cfg(feature = "render")]
 pub mod render_state;
 pub mod rtree;
+#[cfg(feature = "ui")]
 pub mod scene;
+#[cfg(feature = "render")]
 pub mod shader_program;
+#[cfg(feature = "render")]
 pub mod shadow_util;
 pub mod simulation;
+pub mod snapshot;
+pub mod timestep;
+#[cfg(feature = "render")]
 pub mod vertex;
+pub mod world;
@@ -6,6 +6,7 @@ const UP: Vector3<f32> = Vector3::new(0.0, 1.0, 0.0);
 use crate::{
     camera::FirstPersonCamera,
     mesh::DrawMesh,
+    meshes::ColliderMeshes,
     object::Object,
     render_state::{RenderState, SetUniform},
 };
@@ -199,6 +200,8 @@ impl DirectionalLight {
         &mut self,
         render_state: &mut RenderState,
         objects: impl IntoIterator<Item = &'a Object>,
+        meshes: &ColliderMeshes,
+        alpha: f64,
     ) {
         render_state.set_viewport(0, 0, SHADOW_WIDTH, SHADOW_HEIGHT);
         render_state.set_framebuffer(self.shadow_buffer);
@@ -213,8 +216,13 @@ impl DirectionalLight {
             render_state.set_uniform(&format!("view_projs[{i}]"), view_proj);
         }
         for o in objects {
-            render_state.set_uniform("model", &o.model());
-            unsafe { render_state.draw_mesh(&o.mesh) };
+            for (model, collider) in o.interpolated_models(alpha) {
+                let Some(mesh) = meshes.get(&collider) else {
+                    continue;
+                };
+                render_state.set_uniform("model", &model);
+                unsafe { render_state.draw_mesh(mesh) };
+            }
         }
         unsafe {
             render_state.gl().disable(glow::DEPTH_CLAMP);
@@ -1,32 +1,57 @@
-use std::rc::Rc;
-
 use anyhow::Result;
 
 use egui::{DragValue, Ui, Window};
 use glow::HasContext;
 use glutin::surface::GlSurface;
-use nalgebra::{Point3, Rotation3, Scale3, Translation3, Vector3};
+use nalgebra::{Point3, Scale3, Translation3, UnitQuaternion, Vector3};
 use rand::Rng;
 use winit::event::{ElementState, Event, WindowEvent};
 use winit::window::CursorGrabMode;
 
+use crate::aabb::AABB;
+use crate::aerodynamics::Aerodynamics;
 use crate::camera::FirstPersonCamera;
-use crate::collider::Collider;
+use crate::collider::{Collider, CompoundChild};
+use crate::contact_event::ContactEventKind;
+use crate::desync::HashStream;
+use crate::fluid::{Fluid, FluidRegion};
+use crate::force_field::ForceField;
+use crate::integrator::Integrator;
+use crate::joint::Joint;
+use crate::kinematic::{KinematicMotion, KinematicPath};
 use crate::light::{self, DirectionalLight};
+use crate::material::{CombineRule, Material};
 use crate::mesh::{DrawMesh, Mesh};
-use crate::meshes;
-use crate::object::Object;
+use crate::meshes::{self, ColliderMeshes};
+use crate::object::{Damping, ObjectBuilder};
 use crate::recording::Recording;
 use crate::render_state::SetUniform;
 use crate::shader_program::ShaderProgram;
-use crate::simulation::Simulation;
+use crate::simulation::{PositionCorrection, Simulation, EARTH_GRAVITY};
+use crate::snapshot::{Snapshot, SnapshotError};
+use crate::timestep::FixedTimestep;
 use crate::vertex::PVertex;
+use crate::world::{BodyHandle, World};
 use crate::{context::Context, scene::Scene, vertex::PNVertex};
 
+/// Where the checkpoint of the scene is saved, relative to the working
+/// directory.
+const CHECKPOINT_PATH: &str = "checkpoint.bin";
+/// Where the state hashes of the scene are saved, relative to the working
+/// directory.
+const HASHES_PATH: &str = "hashes.bin";
+
+/// The diffuse color of sleeping objects when the bounds are drawn.
+const SLEEPING_TINT: [f32; 3] = [0.4, 0.4, 1.0];
+/// The diffuse color of the object selected for editing.
+const SELECTED_TINT: [f32; 3] = [1.0, 0.6, 0.3];
+/// The diffuse color of sensors.
+const SENSOR_TINT: [f32; 3] = [0.4, 1.0, 0.4];
+
 #[derive(Debug)]
 #[allow(clippy::struct_excessive_bools)]
 pub struct MainScene {
-    objects: Vec<Object>,
+    world: World,
     depth_pass_program: ShaderProgram<PNVertex>,
     phong_shader_program: ShaderProgram<PNVertex>,
     debug_shader_program: ShaderProgram<PVertex>,
@@ -39,8 +64,7 @@ pub struct MainScene {
     camera: FirstPersonCamera,
     bounding_box_mesh: Mesh<PVertex>,
     rectangle_mesh: Mesh<PVertex>,
-    box_mesh: Rc<Mesh<PNVertex>>,
-    sphere_mesh: Rc<Mesh<PNVertex>>,
+    collider_meshes: ColliderMeshes,
     surface_width: f32,
     surface_height: f32,
     paused: bool,
@@ -50,41 +74,50 @@ pub struct MainScene {
     frozen_camera: Option<FirstPersonCamera>,
     recording: Recording,
     selected_frame: usize,
+    /// The in-memory snapshot the simulation can be rolled back to.
+    snapshot: Option<Snapshot>,
+    /// The presets start the random number generator from this seed.
+    seed: u64,
     record: bool,
     playback: bool,
     loop_playback: bool,
+    selected: Option<BodyHandle>,
+    timestep: FixedTimestep,
 }
 
 impl MainScene {
     pub fn new(ctx: &Context) -> Result<Self> {
-        let objects = vec![];
-        let box_mesh = Rc::new(meshes::box_mesh(ctx)?);
-        // let sphere_mesh = Rc::new(meshes::sphere_mesh(ctx, 16, false)?);
-        let sphere_mesh = Rc::new(meshes::icosphere_mesh(ctx, 2)?);
-        let bounding_box_mesh = meshes::bounding_box_mesh(ctx)?;
-        let rectangle_mesh = meshes::rectangle_mesh(ctx)?;
-        let depth_pass_program =
-            ShaderProgram::new(ctx, "src/simple-vs.glsl", "src/empty-fs.glsl")?;
-        let phong_shader_program =
-            ShaderProgram::new(ctx, "src/phong-vs.glsl", "src/phong-fs.glsl")?;
+        let collider_meshes = ColliderMeshes::new(&ctx.gl)?;
+        let bounding_box_mesh = meshes::bounding_box_mesh(&ctx.gl)?;
+        let rectangle_mesh = meshes::rectangle_mesh(&ctx.gl)?;
+        let depth_pass_program = ShaderProgram::new(
+            &ctx.gl,
+            "src/simple-vs.glsl",
+            "src/empty-fs.glsl",
+        )?;
+        let phong_shader_program = ShaderProgram::new(
+            &ctx.gl,
+            "src/phong-vs.glsl",
+            "src/phong-fs.glsl",
+        )?;
         let debug_shader_program = ShaderProgram::new(
-            ctx,
+            &ctx.gl,
             "src/simple-vs.glsl",
             "src/simple_color-fs.glsl",
         )?;
         let hud_shader_program = ShaderProgram::new(
-            ctx,
+            &ctx.gl,
             "src/simple-vs.glsl",
             "src/simple_color-fs.glsl",
         )?;
         let shadow_shader_program = ShaderProgram::with_geometry(
-            ctx,
+            &ctx.gl,
             "src/shadow-vs.glsl",
             "src/shadow-gs.glsl",
             "src/empty-fs.glsl",
         )?;
         Ok(Self {
-            objects,
+            world: World::new(),
             depth_pass_program,
             phong_shader_program,
             debug_shader_program,
@@ -97,8 +130,7 @@ impl MainScene {
             camera: FirstPersonCamera::default(),
             bounding_box_mesh,
             rectangle_mesh,
-            box_mesh,
-            sphere_mesh,
+            collider_meshes,
             surface_width: 1.0,
             surface_height: 1.0,
             paused: false,
@@ -108,272 +140,499 @@ impl MainScene {
             frozen_camera: None,
             recording: Recording::default(),
             selected_frame: 0,
+            snapshot: None,
+            seed: 0,
             record: false,
             playback: false,
             loop_playback: false,
+            selected: None,
+            timestep: FixedTimestep::default(),
         })
     }
 
+    /// Clears the world and every per-scene setting of the simulation,
+    /// so a preset starts from the same state whichever scene was before.
+    fn reset_scene(&mut self, gravity: Vector3<f64>) {
+        self.world.clear();
+        self.simulation.joints.clear();
+        self.simulation.fluids.clear();
+        self.simulation.force_fields.clear();
+        self.simulation.aerodynamics = None;
+        self.simulation.reseed(self.seed);
+        self.simulation.state_hashes.clear();
+        self.simulation.gravity = gravity;
+        self.selected = None;
+        self.recording.clear();
+    }
+
     fn preset_many_things(&mut self) {
-        self.objects.clear();
-        let mut random = rand::thread_rng();
+        self.reset_scene(EARTH_GRAVITY);
+        let random = self.simulation.rng();
         for x in -7..=7 {
             for y in 2..=16 {
                 for z in -7..=7 {
-                    self.objects.push(Object {
-                        position: Point3::new(
-                            f64::from(x)
-                                .mul_add(4.0, random.gen_range(-0.5..=0.5)),
-                            f64::from(y)
-                                .mul_add(4.0, random.gen_range(-0.5..=0.5)),
-                            f64::from(z)
-                                .mul_add(4.0, random.gen_range(-0.5..=0.5)),
-                        ),
-                        rotation: Rotation3::new(Vector3::new(0.0, 0.0, 0.0)),
-                        ..if random.gen() {
-                            let w = random.gen_range(0.5..=3.0);
-                            let h = random.gen_range(0.5..=3.0);
-                            let d = random.gen_range(0.5..=3.0);
-                            Object {
-                                mesh_scale: Vector3::new(
-                                    w as f32, h as f32, d as f32,
-                                ),
-                                ..Object::new(
-                                    &self.box_mesh,
-                                    Collider::Box(w, h, d),
-                                    w * h * d * 8.0,
-                                )
-                            }
-                        } else {
-                            let r = random.gen_range(0.25..=1.5);
-                            Object {
-                                mesh_scale: Vector3::new(
-                                    r as f32, r as f32, r as f32,
-                                ),
-                                ..Object::new(
-                                    &self.sphere_mesh,
-                                    Collider::Sphere(r),
-                                    r * r * r * 8.0,
-                                )
-                            }
-                        }
-                    });
+                    let position = Point3::new(
+                        f64::from(x).mul_add(4.0, random.gen_range(-0.5..=0.5)),
+                        f64::from(y).mul_add(4.0, random.gen_range(-0.5..=0.5)),
+                        f64::from(z).mul_add(4.0, random.gen_range(-0.5..=0.5)),
+                    );
+                    let collider = if random.gen() {
+                        let w = random.gen_range(0.5..=3.0);
+                        let h = random.gen_range(0.5..=3.0);
+                        let d = random.gen_range(0.5..=3.0);
+                        Collider::Box(w, h, d)
+                    } else {
+                        let r = random.gen_range(0.25..=1.5);
+                        Collider::Sphere(r)
+                    };
+                    self.world.insert(
+                        ObjectBuilder::new(collider)
+                            .density(8.0)
+                            .position(position)
+                            .build(),
+                    );
                 }
             }
         }
-        self.objects.push(Object {
-            immovable: true,
-            position: Point3::new(0.0, -10.0, 0.0),
-            mesh_scale: Vector3::new(1000.0, 10.0, 1000.0),
-            ..Object::new(
-                &self.box_mesh,
-                Collider::Box(1000.0, 10.0, 1000.0),
-                1.0,
-            )
-        });
+        self.world.insert(
+            ObjectBuilder::new(Collider::Box(1000.0, 10.0, 1000.0))
+                .position(Point3::new(0.0, -10.0, 0.0))
+                .immovable()
+                .build(),
+        );
     }
 
     fn preset_two_spheres(&mut self) {
-        self.objects.clear();
-        self.objects.push(Object {
-            position: Point3::new(0.0, 0.0, 0.0),
-            rotation: Rotation3::new(Vector3::new(0.0, 0.0, 0.0)),
-            mesh_scale: Vector3::new(1.0, 1.0, 1.0),
-            ..Object::new(&self.sphere_mesh, Collider::Sphere(1.0), 1.0)
-        });
-        self.objects.push(Object {
-            position: Point3::new(2.0, 0.0, 0.0),
-            rotation: Rotation3::new(Vector3::new(0.0, 0.0, 0.0)),
-            mesh_scale: Vector3::new(1.0, 1.0, 1.0),
-            immovable: true,
-            ..Object::new(&self.sphere_mesh, Collider::Sphere(1.0), 1.0)
-        });
+        self.reset_scene(Vector3::zeros());
+        self.world.insert(
+            ObjectBuilder::new(Collider::Sphere(1.0)).mass(1.0).build(),
+        );
+        self.world.insert(
+            ObjectBuilder::new(Collider::Sphere(1.0))
+                .mass(1.0)
+                .position(Point3::new(2.0, 0.0, 0.0))
+                .immovable()
+                .build(),
+        );
     }
 
     fn preset_sphere_and_box(&mut self) {
-        self.objects.clear();
-        self.objects.push(Object {
-            position: Point3::new(0.0, 0.0, 0.0),
-            rotation: Rotation3::new(Vector3::new(0.0, 0.0, 0.0)),
-            mesh_scale: Vector3::new(1.0, 1.0, 1.0),
-            ..Object::new(&self.sphere_mesh, Collider::Sphere(1.0), 1.0)
-        });
-        self.objects.push(Object {
-            position: Point3::new(3.0, 0.0, 0.0),
-            rotation: Rotation3::new(Vector3::new(0.0, 0.0, 0.0)),
-            mesh_scale: Vector3::new(1.5, 1.5, 1.5),
-            ..Object::new(&self.box_mesh, Collider::Box(1.5, 1.5, 1.5), 1.0)
-        });
+        self.reset_scene(Vector3::zeros());
+        self.world.insert(
+            ObjectBuilder::new(Collider::Sphere(1.0)).mass(1.0).build(),
+        );
+        self.world.insert(
+            ObjectBuilder::new(Collider::Box(1.5, 1.5, 1.5))
+                .mass(1.0)
+                .position(Point3::new(3.0, 0.0, 0.0))
+                .build(),
+        );
     }
 
     fn preset_two_boxes(&mut self) {
-        self.objects.clear();
-        self.recording.clear();
-        self.objects.push(Object {
-            position: Point3::new(0.0, 0.0, 0.0),
-            rotation: Rotation3::new(Vector3::new(0.0, 0.0, 0.0)),
-            mesh_scale: Vector3::new(1.5, 1.5, 1.5),
-            ..Object::new(&self.box_mesh, Collider::Box(1.5, 1.5, 1.5), 1.0)
-        });
-        self.objects.push(Object {
-            position: Point3::new(3.0, 0.0, 0.0),
-            rotation: Rotation3::new(Vector3::new(0.0, 0.0, 0.0)),
-            mesh_scale: Vector3::new(1.5, 1.5, 1.5),
-            ..Object::new(&self.box_mesh, Collider::Box(1.5, 1.5, 1.5), 1.0)
-        });
+        self.reset_scene(Vector3::zeros());
+        for x in [0.0, 3.0] {
+            self.world.insert(
+                ObjectBuilder::new(Collider::Box(1.5, 1.5, 1.5))
+                    .mass(1.0)
+                    .position(Point3::new(x, 0.0, 0.0))
+                    .build(),
+            );
+        }
     }
 
     fn preset_wrecking_ball(&mut self) {
-        self.objects.clear();
-        self.recording.clear();
+        self.reset_scene(EARTH_GRAVITY);
         for x in -7..=7 {
             for y in -7..=7 {
                 for z in 0..5 {
-                    self.objects.push(Object {
-                        position: Point3::new(
-                            f64::from(x) * 1.01,
-                            f64::from(y) * 1.01,
-                            f64::from(z) * 1.01,
-                        ),
-                        mesh_scale: Vector3::new(1.0, 1.0, 1.0),
-                        ..Object::new(
-                            &self.box_mesh,
-                            Collider::Box(1.0, 1.0, 1.0),
-                            // Collider::Sphere(0.5),
-                            1.0,
-                        )
-                    });
+                    self.world.insert(
+                        ObjectBuilder::new(Collider::Box(1.0, 1.0, 1.0))
+                            .position(Point3::new(
+                                f64::from(x) * 1.01,
+                                f64::from(y) * 1.01,
+                                f64::from(z) * 1.01,
+                            ))
+                            .build(),
+                    );
                 }
             }
         }
-        self.objects.push(Object {
-            // immovable: true,
-            position: Point3::new(0.0, 0.0, -30.0),
-            momentum: Vector3::new(0.0, 0.0, 450.0),
-            // mesh_scale: Vector3::new(2.0, 2.0, 2.0),
-            // ..Object::new(&self.sphere_mesh, Collider::Sphere(2.0), 20.0)
-            mesh_scale: Vector3::new(4.0, 4.0, 4.0),
-            ..Object::new(&self.box_mesh, Collider::Box(4.0, 4.0, 4.0), 20.0)
-        });
-        self.objects.push(Object {
-            immovable: true,
-            position: Point3::new(0.0, -15.0, 0.0),
-            mesh_scale: Vector3::new(1000.0, 10.0, 1000.0),
-            ..Object::new(
-                &self.box_mesh,
-                Collider::Box(1000.0, 10.0, 1000.0),
-                1.0,
-            )
-        });
+        let anchor = Point3::new(0.0, 30.0, 0.0);
+        let ball = self.world.insert(
+            ObjectBuilder::new(Collider::Sphere(2.5))
+                .mass(100.0)
+                .position(Point3::new(0.0, 15.0, -25.98))
+                .ccd()
+                .build(),
+        );
+        let position = self.world[ball].position();
+        let rope = Joint::rope(&self.world, ball, None, position, anchor)
+            .expect("the ball is tied to the world");
+        self.simulation.joints.push(rope);
+        self.world.insert(
+            ObjectBuilder::new(Collider::Box(1000.0, 10.0, 1000.0))
+                .position(Point3::new(0.0, -15.0, 0.0))
+                .immovable()
+                .build(),
+        );
+    }
+
+    fn preset_materials(&mut self) {
+        self.reset_scene(EARTH_GRAVITY);
+        let materials = [Material::ICE, Material::RUBBER, Material::STEEL];
+        for (x, material) in [-6.0, 0.0, 6.0].into_iter().zip(materials) {
+            // bouncing balls
+            self.world.insert(
+                ObjectBuilder::new(Collider::Sphere(1.0))
+                    .material(material)
+                    .position(Point3::new(x, 10.0, -5.0))
+                    .build(),
+            );
+            // sliding boxes
+            self.world.insert(
+                ObjectBuilder::new(Collider::Box(1.0, 1.0, 1.0))
+                    .material(material)
+                    .position(Point3::new(x, 0.5, 5.0))
+                    .velocity(Vector3::new(0.0, 0.0, 8.0))
+                    .build(),
+            );
+        }
+        self.world.insert(
+            ObjectBuilder::new(Collider::Box(1000.0, 10.0, 1000.0))
+                .material(Material::STEEL)
+                .position(Point3::new(0.0, -5.0, 0.0))
+                .immovable()
+                .build(),
+        );
+        // the goal zone of the sliding boxes
+        self.world.insert(
+            ObjectBuilder::new(Collider::Box(20.0, 0.1, 4.0))
+                .position(Point3::new(0.0, 0.05, 12.0))
+                .immovable()
+                .sensor()
+                .build(),
+        );
     }
 
     fn preres_carpet_bomb(&mut self) {
-        self.objects.clear();
-        self.recording.clear();
+        self.reset_scene(EARTH_GRAVITY);
         for x in -50..=50 {
             for z in -50..=50 {
-                self.objects.push(Object {
-                    position: Point3::new(
-                        f64::from(x) * 1.01,
-                        f64::from(0) * 1.01,
-                        f64::from(z) * 1.01,
-                    ),
-                    mesh_scale: Vector3::new(1.0, 1.0, 1.0),
-                    ..Object::new(
-                        &self.box_mesh,
-                        Collider::Box(1.0, 1.0, 1.0),
-                        // Collider::Sphere(0.5),
-                        1.0,
-                    )
-                });
+                self.world.insert(
+                    ObjectBuilder::new(Collider::Box(1.0, 1.0, 1.0))
+                        .position(Point3::new(
+                            f64::from(x) * 1.01,
+                            f64::from(0) * 1.01,
+                            f64::from(z) * 1.01,
+                        ))
+                        .build(),
+                );
             }
         }
         for x in -3..=3 {
             for z in -3..=3 {
-                self.objects.push(Object {
-                    position: Point3::new(
-                        f64::from(x) * 15.0,
-                        f64::from((x + 5) * 7 + z + 5) * 15.0,
-                        f64::from(z) * 15.0,
-                    ),
-                    momentum: Vector3::new(0.0, -800.0, 0.0),
-                    mesh_scale: Vector3::new(4.0, 4.0, 4.0),
-                    ..Object::new(
-                        &self.box_mesh,
-                        Collider::Box(4.0, 4.0, 4.0),
-                        // Collider::Sphere(0.5),
-                        20.0,
-                    )
-                });
+                self.world.insert(
+                    ObjectBuilder::new(Collider::Box(4.0, 4.0, 4.0))
+                        .mass(20.0)
+                        .position(Point3::new(
+                            f64::from(x) * 15.0,
+                            f64::from((x + 5) * 7 + z + 5) * 15.0,
+                            f64::from(z) * 15.0,
+                        ))
+                        .velocity(Vector3::new(0.0, -40.0, 0.0))
+                        .ccd()
+                        .build(),
+                );
             }
         }
     }
 
     fn preset_spinning_ball(&mut self) {
-        self.objects.clear();
-        self.recording.clear();
+        self.reset_scene(Vector3::zeros());
+        self.push_sphere_grid();
+        self.world.insert(
+            ObjectBuilder::new(Collider::Sphere(20.0))
+                .mass(100_000.0)
+                .position(Point3::new(0.0, 0.0, 50.0))
+                .kinematic(KinematicMotion::Velocity {
+                    linear: Vector3::zeros(),
+                    angular: Vector3::new(0.0, 0.1875, 0.0),
+                })
+                .build(),
+        );
+    }
+
+    fn preset_rotating_board(&mut self) {
+        self.reset_scene(Vector3::zeros());
+        self.push_sphere_grid();
+        self.world.insert(
+            ObjectBuilder::new(Collider::Box(40.0, 10.0, 1.0))
+                .mass(100.0)
+                .position(Point3::new(0.0, 0.0, 30.0))
+                .kinematic(KinematicMotion::Velocity {
+                    linear: Vector3::zeros(),
+                    angular: Vector3::new(0.0, 0.75, 0.0),
+                })
+                .build(),
+        );
+    }
+
+    /// The floating spheres of the spinning ball and rotating board presets,
+    /// they are damped so they calm down after being hit.
+    fn push_sphere_grid(&mut self) {
         for x in -5..=5 {
             for y in -5..=5 {
-                self.objects.push(Object {
-                    position: Point3::new(
-                        1.5 * f64::from(x),
-                        1.5 * f64::from(y),
-                        0.0,
-                    ),
-                    mesh_scale: Vector3::new(0.5, 0.5, 0.5),
-                    ..Object::new(&self.sphere_mesh, Collider::Sphere(0.5), 1.0)
-                });
+                self.world.insert(
+                    ObjectBuilder::new(Collider::Sphere(0.5))
+                        .mass(1.0)
+                        .damping(Damping {
+                            linear: 0.1,
+                            angular: 0.5,
+                        })
+                        .position(Point3::new(
+                            1.5 * f64::from(x),
+                            1.5 * f64::from(y),
+                            0.0,
+                        ))
+                        .build(),
+                );
             }
         }
-        self.objects.push(Object {
-            position: Point3::new(0.0, 0.0, 50.0),
-            angular_momentum: Vector3::new(0.0, 5e6, 0.0),
-            mesh_scale: Vector3::new(20.0, 20.0, 20.0),
-            immovable: true,
-            ..Object::new(&self.sphere_mesh, Collider::Sphere(20.0), 100_000.0)
-        });
     }
 
-    fn preset_rotating_board(&mut self) {
-        self.objects.clear();
-        self.recording.clear();
-        for x in -5..=5 {
-            for y in -5..=5 {
-                self.objects.push(Object {
-                    position: Point3::new(
-                        1.5 * f64::from(x),
-                        1.5 * f64::from(y),
-                        0.0,
-                    ),
-                    mesh_scale: Vector3::new(0.5, 0.5, 0.5),
-                    ..Object::new(&self.sphere_mesh, Collider::Sphere(0.5), 1.0)
-                });
+    /// A box spinning around its intermediate axis flips over periodically,
+    /// the integrators can be compared by the energy they gain or lose.
+    fn preset_tumbling_box(&mut self) {
+        self.reset_scene(Vector3::zeros());
+        self.world.insert(
+            ObjectBuilder::new(Collider::Box(1.0, 3.0, 5.0))
+                .mass(1.0)
+                .position(Point3::new(0.0, 5.0, 0.0))
+                .angular_velocity(Vector3::new(0.02, 9.0, 0.0))
+                .build(),
+        );
+    }
+
+    fn preset_moving_platform(&mut self) {
+        self.reset_scene(EARTH_GRAVITY);
+        let path = KinematicPath::keyframes(
+            vec![
+                (0.0, Point3::new(0.0, 0.0, 0.0), UnitQuaternion::identity()),
+                (3.0, Point3::new(0.0, 8.0, 0.0), UnitQuaternion::identity()),
+                (
+                    6.0,
+                    Point3::new(12.0, 8.0, 0.0),
+                    UnitQuaternion::new(Vector3::new(0.0, 0.0, 0.3)),
+                ),
+                (9.0, Point3::new(12.0, 0.0, 0.0), UnitQuaternion::identity()),
+                (12.0, Point3::new(0.0, 0.0, 0.0), UnitQuaternion::identity()),
+            ],
+            true,
+        );
+        self.world.insert(
+            ObjectBuilder::new(Collider::Box(8.0, 0.5, 8.0))
+                .mass(1.0)
+                .kinematic(KinematicMotion::Path(path))
+                .build(),
+        );
+        for x in -1..=1 {
+            for z in -1..=1 {
+                self.world.insert(
+                    ObjectBuilder::new(Collider::Box(1.0, 1.0, 1.0))
+                        .position(Point3::new(
+                            f64::from(x) * 2.0,
+                            1.0,
+                            f64::from(z) * 2.0,
+                        ))
+                        .build(),
+                );
             }
         }
-        self.objects.push(Object {
-            position: Point3::new(0.0, 0.0, 30.0),
-            angular_momentum: Vector3::new(0.0, 1e4, 0.0),
-            mesh_scale: Vector3::new(40.0, 10.0, 1.0),
-            immovable: true,
-            ..Object::new(&self.box_mesh, Collider::Box(40.0, 10.0, 1.0), 100.0)
+        self.world.insert(
+            ObjectBuilder::new(Collider::Box(1000.0, 10.0, 1000.0))
+                .position(Point3::new(0.0, -10.0, 0.0))
+                .immovable()
+                .build(),
+        );
+    }
+
+    /// Objects with different densities dropped into a tank of water,
+    /// the light ones float and the long boxes tip over onto their side.
+    fn preset_water_tank(&mut self) {
+        self.reset_scene(EARTH_GRAVITY);
+        self.simulation.fluids.push(Fluid {
+            region: FluidRegion::Tank(AABB::new(
+                Point3::new(-15.0, -10.0, -15.0),
+                Point3::new(15.0, 0.0, 15.0),
+            )),
+            density: Fluid::WATER_DENSITY,
+            linear_drag: 1.0,
+            angular_drag: 1.0,
         });
+        for (x, density) in
+            [-9.0, -3.0, 3.0, 9.0].into_iter().zip([0.2, 0.5, 0.8, 3.0])
+        {
+            self.world.insert(
+                ObjectBuilder::new(Collider::Sphere(1.0))
+                    .density(density)
+                    .position(Point3::new(x, 4.0, -5.0))
+                    .build(),
+            );
+            self.world.insert(
+                ObjectBuilder::new(Collider::Box(1.5, 1.5, 1.5))
+                    .density(density)
+                    .position(Point3::new(x, 4.0, 0.0))
+                    .build(),
+            );
+            self.world.insert(
+                ObjectBuilder::new(Collider::Box(1.0, 4.0, 1.0))
+                    .density(density)
+                    .position(Point3::new(x, 4.0, 5.0))
+                    .build(),
+            );
+        }
+        self.world.insert(
+            ObjectBuilder::new(Collider::Box(1000.0, 10.0, 1000.0))
+                .position(Point3::new(0.0, -15.0, 0.0))
+                .immovable()
+                .build(),
+        );
+    }
+
+    /// Balls kicked with no spin, backspin, topspin and sidespin,
+    /// the spin curves their path through the air.
+    fn preset_curve_balls(&mut self) {
+        self.reset_scene(EARTH_GRAVITY);
+        self.simulation.aerodynamics = Some(Aerodynamics::default());
+        let spins = [
+            Vector3::zeros(),
+            Vector3::new(-40.0, 0.0, 0.0),
+            Vector3::new(40.0, 0.0, 0.0),
+            Vector3::new(0.0, 40.0, 0.0),
+        ];
+        for (x, spin) in [-6.0, -2.0, 2.0, 6.0].into_iter().zip(spins) {
+            self.world.insert(
+                ObjectBuilder::new(Collider::Sphere(0.5))
+                    // the density of a football
+                    .density(0.08)
+                    .position(Point3::new(x, 0.5, -20.0))
+                    .velocity(Vector3::new(0.0, 8.0, 25.0))
+                    .angular_velocity(spin)
+                    .build(),
+            );
+        }
+        self.world.insert(
+            ObjectBuilder::new(Collider::Box(1000.0, 10.0, 1000.0))
+                .position(Point3::new(0.0, -5.0, 0.0))
+                .immovable()
+                .build(),
+        );
+    }
+
+    /// Tables, dumbbells and L shapes made of several parts, they tumble
+    /// and come to rest on their parts.
+    fn preset_compound_shapes(&mut self) {
+        self.reset_scene(EARTH_GRAVITY);
+        let no_rotation = UnitQuaternion::identity();
+        let mut table = vec![CompoundChild::new(
+            Collider::Box(3.0, 0.2, 2.0),
+            Point3::new(0.0, 1.0, 0.0),
+            no_rotation,
+        )];
+        for (x, z) in [(-1.3, -0.8), (1.3, -0.8), (-1.3, 0.8), (1.3, 0.8)] {
+            table.push(CompoundChild::new(
+                Collider::Box(0.2, 1.0, 0.2),
+                Point3::new(x, 0.4, z),
+                no_rotation,
+            ));
+        }
+        let table = Collider::compound(table);
+        let dumbbell = Collider::compound([
+            CompoundChild::new(
+                Collider::Sphere(0.5),
+                Point3::new(-1.0, 0.0, 0.0),
+                no_rotation,
+            ),
+            CompoundChild::new(
+                Collider::Sphere(0.5),
+                Point3::new(1.0, 0.0, 0.0),
+                no_rotation,
+            ),
+            CompoundChild::new(
+                Collider::Box(0.2, 1.5, 0.2),
+                Point3::origin(),
+                UnitQuaternion::from_axis_angle(
+                    &Vector3::z_axis(),
+                    std::f64::consts::FRAC_PI_2,
+                ),
+            ),
+        ]);
+        let l_shape = Collider::compound([
+            CompoundChild::new(
+                Collider::Box(2.0, 0.5, 0.5),
+                Point3::new(0.75, 0.0, 0.0),
+                no_rotation,
+            ),
+            CompoundChild::new(
+                Collider::Box(0.5, 1.5, 0.5),
+                Point3::new(0.0, 1.0, 0.0),
+                no_rotation,
+            ),
+        ]);
+        let shapes = [table, dumbbell, l_shape]
+            .map(|shape| shape.expect("the preset shapes have parts"));
+        for (x, shape) in [-6.0, 0.0, 6.0].into_iter().zip(shapes) {
+            for level in 0..3 {
+                self.world.insert(
+                    ObjectBuilder::new(shape.clone())
+                        .position(Point3::new(
+                            x,
+                            4.0f64.mul_add(f64::from(level), 2.0),
+                            0.0,
+                        ))
+                        .rotation(UnitQuaternion::new(
+                            Vector3::new(0.3, 0.5, 0.2) * f64::from(level),
+                        ))
+                        .build(),
+                );
+            }
+        }
+        self.world.insert(
+            ObjectBuilder::new(Collider::Box(1000.0, 10.0, 1000.0))
+                .position(Point3::new(0.0, -5.0, 0.0))
+                .immovable()
+                .build(),
+        );
     }
 
     fn depth_pass(&self, ctx: &mut Context) {
+        let alpha = self.render_alpha();
         ctx.render_state.set_program(&self.depth_pass_program);
         ctx.render_state
             .set_uniform("view_proj", &self.camera.view_proj());
-        for object in &self.objects {
-            ctx.render_state.set_uniform("model", &object.model());
-            unsafe { ctx.render_state.draw_mesh(&object.mesh) };
+        for object in self.world.objects() {
+            for (model, collider) in object.interpolated_models(alpha) {
+                let Some(mesh) = self.collider_meshes.get(&collider) else {
+                    continue;
+                };
+                ctx.render_state.set_uniform("model", &model);
+                unsafe { ctx.render_state.draw_mesh(mesh) };
+            }
         }
     }
 
     fn draw_shadow(&mut self, ctx: &mut Context) {
+        let alpha = self.render_alpha();
         unsafe {
             ctx.render_state.set_program(&self.shadow_shader_program);
             for light in &mut self.lights {
-                light.render_shadows(&mut ctx.render_state, &self.objects);
+                light.render_shadows(
+                    &mut ctx.render_state,
+                    self.world.objects(),
+                    &self.collider_meshes,
+                    alpha,
+                );
             }
         }
         ctx.render_state.set_cull_face(glow::BACK);
@@ -387,6 +646,7 @@ impl MainScene {
     }
 
     fn draw_phong(&self, ctx: &mut Context) {
+        let alpha = self.render_alpha();
         ctx.render_state.set_program(&self.phong_shader_program);
         ctx.render_state.set_uniform(
             "directional_light_count",
@@ -422,12 +682,28 @@ impl MainScene {
             .set_uniform("wEye", &self.camera.position().coords);
         ctx.render_state
             .set_uniform("view_proj", &self.camera.view_proj());
-        for object in &self.objects {
-            let model_m = object.model();
-            ctx.render_state.set_uniform("model", &model_m);
-            ctx.render_state
-                .set_uniform("model_inv", &model_m.try_inverse().unwrap());
-            unsafe { ctx.render_state.draw_mesh(&object.mesh) };
+        for (handle, object) in self.world.iter() {
+            ctx.render_state.set_uniform(
+                "kd",
+                if self.selected == Some(handle) {
+                    &SELECTED_TINT
+                } else if object.sensor {
+                    &SENSOR_TINT
+                } else if self.draw_debug && object.sleeping {
+                    &SLEEPING_TINT
+                } else {
+                    &[1.0, 1.0, 1.0]
+                },
+            );
+            for (model_m, collider) in object.interpolated_models(alpha) {
+                let Some(mesh) = self.collider_meshes.get(&collider) else {
+                    continue;
+                };
+                ctx.render_state.set_uniform("model", &model_m);
+                ctx.render_state
+                    .set_uniform("model_inv", &model_m.try_inverse().unwrap());
+                unsafe { ctx.render_state.draw_mesh(mesh) };
+            }
         }
     }
 
@@ -457,6 +733,20 @@ impl MainScene {
                 unsafe { ctx.render_state.draw_mesh(&self.bounding_box_mesh) };
             }
         }
+        ctx.render_state.set_line_width(2.0);
+        ctx.render_state.set_uniform("color", &[0.2, 0.5, 1.0]);
+        for fluid in &self.simulation.fluids {
+            let FluidRegion::Tank(tank) = &fluid.region else {
+                continue;
+            };
+            let size = tank.end() - tank.start();
+            let pos = tank.start() + size / 2.0;
+            let model_m = Translation3::from(pos.cast::<f32>())
+                .to_homogeneous()
+                * Scale3::from(size.cast::<f32>()).to_homogeneous();
+            ctx.render_state.set_uniform("model", &model_m);
+            unsafe { ctx.render_state.draw_mesh(&self.bounding_box_mesh) };
+        }
     }
 
     fn draw_shadow_frustums(&self, ctx: &mut Context) {
@@ -509,14 +799,26 @@ impl MainScene {
         if ui.checkbox(&mut self.paused, "Pause").changed() {
             if self.paused {
                 self.recording
-                    .load_frame_to(self.selected_frame, &mut self.objects);
+                    .load_frame_to(self.selected_frame, &mut self.world);
             } else {
                 self.recording.load_frame_to(
                     self.recording.last_frame_index(),
-                    &mut self.objects,
+                    &mut self.world,
                 );
             }
         }
+        ui.add(
+            DragValue::new(&mut self.timestep.time_scale)
+                .prefix("Time scale: ")
+                .clamp_range(0.0..=4.0)
+                .speed(0.01),
+        );
+        ui.add(
+            DragValue::new(&mut self.timestep.max_steps)
+                .prefix("Max steps per frame: ")
+                .clamp_range(1..=100)
+                .speed(0.1),
+        );
         ui.checkbox(&mut self.record, "Record");
         if ui
             .add_enabled(
@@ -530,10 +832,16 @@ impl MainScene {
             && self.paused
         {
             self.recording
-                .load_frame_to(self.selected_frame, &mut self.objects);
+                .load_frame_to(self.selected_frame, &mut self.world);
         };
         ui.checkbox(&mut self.playback, "Playback");
         ui.checkbox(&mut self.loop_playback, "Loop playback");
+        self.draw_snapshot_ui(ui);
+        self.draw_hash_ui(ui);
+        ui.horizontal(|ui| {
+            ui.add(DragValue::new(&mut self.seed).prefix("Seed: "));
+            ui.label(format!("Scene seed: {}", self.simulation.seed()));
+        });
         if ui.button("Many things").clicked() {
             self.preset_many_things();
         }
@@ -546,7 +854,7 @@ impl MainScene {
         if ui.button("Two boxes").clicked() {
             self.preset_two_boxes();
         }
-        if ui.button("Wrecking ball (cube)").clicked() {
+        if ui.button("Wrecking ball").clicked() {
             self.preset_wrecking_ball();
         }
         if ui.button("Spinning ball").clicked() {
@@ -555,9 +863,27 @@ impl MainScene {
         if ui.button("Rotating board").clicked() {
             self.preset_rotating_board();
         }
+        if ui.button("Tumbling box").clicked() {
+            self.preset_tumbling_box();
+        }
+        if ui.button("Moving platform").clicked() {
+            self.preset_moving_platform();
+        }
         if ui.button("Carpet bomb").clicked() {
             self.preres_carpet_bomb();
         }
+        if ui.button("Materials").clicked() {
+            self.preset_materials();
+        }
+        if ui.button("Water tank").clicked() {
+            self.preset_water_tank();
+        }
+        if ui.button("Curve balls").clicked() {
+            self.preset_curve_balls();
+        }
+        if ui.button("Compound shapes").clicked() {
+            self.preset_compound_shapes();
+        }
         ui.checkbox(&mut self.depth_pass, "Depth pass");
         ui.checkbox(&mut self.draw_phong, "Draw objects");
         ui.checkbox(&mut self.draw_debug, "Draw bounds");
@@ -577,12 +903,80 @@ impl MainScene {
                 .clamp_range(1..=10)
                 .speed(0.1),
         );
+        combine_rule_radio(
+            ui,
+            "Restitution combine: ",
+            &mut self.simulation.restitution_combine,
+        );
+        combine_rule_radio(
+            ui,
+            "Friction combine: ",
+            &mut self.simulation.friction_combine,
+        );
+        ui.add(
+            DragValue::new(&mut self.simulation.restitution_threshold)
+                .prefix("Restitution threshold: ")
+                .clamp_range(0.0..=10.0)
+                .speed(0.01),
+        );
+        integrator_radio(ui, &mut self.simulation.integrator);
+        ui.add(
+            DragValue::new(&mut self.simulation.threads)
+                .prefix("Threads: ")
+                .clamp_range(1..=64)
+                .speed(0.1),
+        );
         ui.add(
-            DragValue::new(&mut self.simulation.epsilon)
-                .prefix("Collision energy multiplier: ")
-                .clamp_range(-1.0..=2.0)
+            DragValue::new(&mut self.simulation.solver_iterations)
+                .prefix("Solver iterations: ")
+                .clamp_range(1..=100)
+                .speed(0.1),
+        );
+        ui.horizontal(|ui| {
+            ui.label("Position correction: ");
+            for (correction, name) in [
+                (PositionCorrection::None, "None"),
+                (PositionCorrection::Baumgarte, "Baumgarte"),
+                (PositionCorrection::SplitImpulse, "Split impulse"),
+            ] {
+                ui.radio_value(
+                    &mut self.simulation.position_correction,
+                    correction,
+                    name,
+                );
+            }
+        });
+        ui.add(
+            DragValue::new(&mut self.simulation.penetration_slop)
+                .prefix("Penetration slop: ")
+                .clamp_range(0.0..=1.0)
+                .speed(0.001),
+        );
+        ui.add(
+            DragValue::new(&mut self.simulation.correction_factor)
+                .prefix("Correction factor: ")
+                .clamp_range(0.0..=1.0)
                 .speed(0.005),
         );
+        ui.checkbox(&mut self.simulation.allow_sleeping, "Allow sleeping");
+        ui.add(
+            DragValue::new(&mut self.simulation.sleep_linear_threshold)
+                .prefix("Sleep velocity threshold: ")
+                .clamp_range(0.0..=10.0)
+                .speed(0.001),
+        );
+        ui.add(
+            DragValue::new(&mut self.simulation.sleep_angular_threshold)
+                .prefix("Sleep angular velocity threshold: ")
+                .clamp_range(0.0..=10.0)
+                .speed(0.001),
+        );
+        ui.add(
+            DragValue::new(&mut self.simulation.time_to_sleep)
+                .prefix("Time to sleep: ")
+                .clamp_range(0.0..=10.0)
+                .speed(0.01),
+        );
         ui.separator();
         let mut remove = None;
         for (i, light) in self.lights.iter_mut().enumerate() {
@@ -610,25 +1004,63 @@ impl MainScene {
             self.lights.push(DirectionalLight::new(gl));
         }
         ui.separator();
-        ui.add(
-            DragValue::new(&mut self.simulation.mu)
-                .prefix("Coefficient of friction: ")
-                .clamp_range(-1.0..=2.0)
-                .speed(0.005),
+        self.draw_force_ui(ui);
+        ui.separator();
+        aerodynamics_ui(ui, &mut self.simulation.aerodynamics);
+        ui.separator();
+        self.draw_fluid_ui(ui);
+        ui.separator();
+        self.draw_selected_ui(ui);
+        ui.separator();
+        let (begin, end) = self.simulation.contact_events().iter().fold(
+            (0, 0),
+            |(begin, end), event| match event.kind {
+                ContactEventKind::Begin => (begin + 1, end),
+                ContactEventKind::Persist => (begin, end),
+                ContactEventKind::End => (begin, end + 1),
+            },
         );
+        ui.label(format!(
+            "Contacts: {}, began: {begin}, ended: {end}",
+            self.simulation.contact_events().len() - end
+        ));
+        for (i, (handle, object)) in self.world.iter().enumerate() {
+            if object.sensor {
+                let count = self
+                    .simulation
+                    .sensor_overlaps()
+                    .iter()
+                    .filter(|(sensor, _)| *sensor == handle)
+                    .count();
+                ui.label(format!("Sensor {i}: {count} objects inside"));
+            }
+        }
         let total_momentum = self
-            .objects
+            .world
+            .objects()
             .iter()
             .map(|o| o.momentum)
             .sum::<Vector3<f64>>();
         ui.label(format!("Total momentum: {}", total_momentum.magnitude()));
+        let total_angular_momentum = self
+            .world
+            .objects()
+            .iter()
+            .map(|o| o.angular_momentum)
+            .sum::<Vector3<f64>>();
+        ui.label(format!(
+            "Total angular momentum: {}",
+            total_angular_momentum.magnitude()
+        ));
         let total_directional_energy = self
-            .objects
+            .world
+            .objects()
             .iter()
-            .map(|o| o.momentum.magnitude_squared() / o.mass / 2.0)
+            .map(|o| o.momentum.magnitude_squared() / o.mass() / 2.0)
             .sum::<f64>();
         let total_rotational_energy = self
-            .objects
+            .world
+            .objects()
             .iter()
             .map(|o| {
                 (o.angular_momentum.transpose()
@@ -650,11 +1082,445 @@ impl MainScene {
         ));
     }
 
+    fn draw_force_ui(&mut self, ui: &mut Ui) {
+        vector_drag_value(ui, "Gravity: ", &mut self.simulation.gravity, 0.05);
+        damping_drag_values(ui, &mut self.simulation.damping);
+        let mut remove = None;
+        for (i, field) in self.simulation.force_fields.iter_mut().enumerate() {
+            ui.label(format!("Force field {i}: {}", field.name()));
+            match field {
+                ForceField::Uniform { force } => {
+                    vector_drag_value(ui, "Force: ", force, 0.05);
+                }
+                ForceField::PointAttractor {
+                    center,
+                    strength,
+                    min_distance,
+                } => {
+                    vector_drag_value(ui, "Center: ", &mut center.coords, 0.1);
+                    ui.add(
+                        DragValue::new(strength)
+                            .prefix("Strength: ")
+                            .speed(1.0),
+                    );
+                    ui.add(
+                        DragValue::new(min_distance)
+                            .prefix("Min distance: ")
+                            .clamp_range(0.01..=f64::INFINITY)
+                            .speed(0.05),
+                    );
+                }
+                ForceField::Wind { volume, force } => {
+                    let mut start = *volume.start();
+                    let mut end = *volume.end();
+                    vector_drag_value(ui, "Start: ", &mut start.coords, 0.1);
+                    vector_drag_value(ui, "End: ", &mut end.coords, 0.1);
+                    let (start, end) = start.inf_sup(&end);
+                    *volume = AABB::new(start, end);
+                    vector_drag_value(ui, "Force: ", force, 0.05);
+                }
+                ForceField::Vortex {
+                    center,
+                    axis,
+                    strength,
+                    radius,
+                } => {
+                    vector_drag_value(ui, "Center: ", &mut center.coords, 0.1);
+                    vector_drag_value(ui, "Axis: ", axis, 0.01);
+                    ui.add(
+                        DragValue::new(strength)
+                            .prefix("Strength: ")
+                            .speed(0.1),
+                    );
+                    ui.add(
+                        DragValue::new(radius)
+                            .prefix("Radius: ")
+                            .clamp_range(0.0..=f64::INFINITY)
+                            .speed(0.1),
+                    );
+                }
+            }
+            if remove.is_none()
+                && ui.button(format!("Remove force field {i}")).clicked()
+            {
+                remove = Some(i);
+            }
+        }
+        if let Some(index) = remove {
+            self.simulation.force_fields.remove(index);
+        }
+        ui.horizontal(|ui| {
+            ui.label("Add force field: ");
+            if ui.button("Uniform").clicked() {
+                self.simulation.force_fields.push(ForceField::Uniform {
+                    force: Vector3::new(10.0, 0.0, 0.0),
+                });
+            }
+            if ui.button("Attractor").clicked() {
+                self.simulation
+                    .force_fields
+                    .push(ForceField::PointAttractor {
+                        center: Point3::new(0.0, 10.0, 0.0),
+                        strength: 1000.0,
+                        min_distance: 1.0,
+                    });
+            }
+            if ui.button("Wind").clicked() {
+                self.simulation.force_fields.push(ForceField::Wind {
+                    volume: AABB::new(
+                        Point3::new(-10.0, 0.0, -10.0),
+                        Point3::new(10.0, 20.0, 10.0),
+                    ),
+                    force: Vector3::new(0.0, 0.0, 20.0),
+                });
+            }
+            if ui.button("Vortex").clicked() {
+                self.simulation.force_fields.push(ForceField::Vortex {
+                    center: Point3::new(0.0, 0.0, 0.0),
+                    axis: Vector3::y(),
+                    strength: 20.0,
+                    radius: 20.0,
+                });
+            }
+        });
+    }
+
+    fn draw_snapshot_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("Take snapshot").clicked() {
+                self.snapshot = Some(self.simulation.snapshot(&self.world));
+            }
+            if let Some(snapshot) = &self.snapshot {
+                if ui.button("Roll back").clicked() {
+                    self.restore(&snapshot.clone());
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Save checkpoint").clicked() {
+                let snapshot = self.simulation.snapshot(&self.world);
+                if let Err(error) = std::fs::File::create(CHECKPOINT_PATH)
+                    .and_then(|file| {
+                        snapshot.write_to(std::io::BufWriter::new(file))
+                    })
+                {
+                    eprintln!("Cannot save {CHECKPOINT_PATH}: {error}");
+                }
+            }
+            if ui.button("Load checkpoint").clicked() {
+                match std::fs::File::open(CHECKPOINT_PATH)
+                    .map_err(SnapshotError::from)
+                    .and_then(|file| {
+                        Snapshot::read_from(std::io::BufReader::new(file))
+                    }) {
+                    Ok(snapshot) => self.restore(&snapshot),
+                    Err(error) => {
+                        eprintln!("Cannot load {CHECKPOINT_PATH}: {error}");
+                    }
+                }
+            }
+        });
+    }
+
+    fn draw_hash_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.simulation.hash_states, "Hash states");
+            if let Some(step) = self.simulation.state_hashes.last() {
+                ui.label(format!(
+                    "Step {}: {:016x}",
+                    self.simulation.state_hashes.steps().len() - 1,
+                    step.hash()
+                ));
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Save hashes").clicked() {
+                if let Err(error) =
+                    std::fs::File::create(HASHES_PATH).and_then(|file| {
+                        self.simulation
+                            .state_hashes
+                            .write_to(std::io::BufWriter::new(file))
+                    })
+                {
+                    eprintln!("Cannot save {HASHES_PATH}: {error}");
+                }
+            }
+            if ui.button("Compare hashes").clicked() {
+                match std::fs::File::open(HASHES_PATH).and_then(|file| {
+                    HashStream::read_from(std::io::BufReader::new(file))
+                }) {
+                    Ok(expected) => {
+                        match expected
+                            .find_desync(&self.simulation.state_hashes)
+                        {
+                            Some(desync) => println!("Desync: {desync}"),
+                            None => println!("No desync"),
+                        }
+                    }
+                    Err(error) => {
+                        eprintln!("Cannot load {HASHES_PATH}: {error}");
+                    }
+                }
+            }
+        });
+    }
+
+    /// Restores the snapshot, the recording starts over because it would
+    /// jump in time.
+    fn restore(&mut self, snapshot: &Snapshot) {
+        match self.simulation.restore(&mut self.world, snapshot) {
+            Ok(()) => self.recording.clear(),
+            Err(error) => eprintln!("Cannot restore the snapshot: {error}"),
+        }
+    }
+
+    fn draw_fluid_ui(&mut self, ui: &mut Ui) {
+        let mut remove = None;
+        for (i, fluid) in self.simulation.fluids.iter_mut().enumerate() {
+            ui.label(format!("Fluid {i}: {}", fluid.name()));
+            match &mut fluid.region {
+                FluidRegion::HalfSpace { surface_level } => {
+                    ui.add(
+                        DragValue::new(surface_level)
+                            .prefix("Surface level: ")
+                            .speed(0.1),
+                    );
+                }
+                FluidRegion::Tank(tank) => {
+                    let mut start = *tank.start();
+                    let mut end = *tank.end();
+                    vector_drag_value(ui, "Start: ", &mut start.coords, 0.1);
+                    vector_drag_value(ui, "End: ", &mut end.coords, 0.1);
+                    let (start, end) = start.inf_sup(&end);
+                    *tank = AABB::new(start, end);
+                }
+            }
+            ui.add(
+                DragValue::new(&mut fluid.density)
+                    .prefix("Density: ")
+                    .clamp_range(0.0..=f64::INFINITY)
+                    .speed(0.01),
+            );
+            ui.add(
+                DragValue::new(&mut fluid.linear_drag)
+                    .prefix("Linear drag: ")
+                    .clamp_range(0.0..=f64::INFINITY)
+                    .speed(0.01),
+            );
+            ui.add(
+                DragValue::new(&mut fluid.angular_drag)
+                    .prefix("Angular drag: ")
+                    .clamp_range(0.0..=f64::INFINITY)
+                    .speed(0.01),
+            );
+            if remove.is_none()
+                && ui.button(format!("Remove fluid {i}")).clicked()
+            {
+                remove = Some(i);
+            }
+        }
+        if let Some(index) = remove {
+            self.simulation.fluids.remove(index);
+        }
+        ui.horizontal(|ui| {
+            ui.label("Add fluid: ");
+            if ui.button("Ocean").clicked() {
+                self.simulation.fluids.push(Fluid {
+                    region: FluidRegion::HalfSpace { surface_level: 0.0 },
+                    density: Fluid::WATER_DENSITY,
+                    linear_drag: 1.0,
+                    angular_drag: 1.0,
+                });
+            }
+            if ui.button("Tank").clicked() {
+                self.simulation.fluids.push(Fluid {
+                    region: FluidRegion::Tank(AABB::new(
+                        Point3::new(-10.0, -10.0, -10.0),
+                        Point3::new(10.0, 0.0, 10.0),
+                    )),
+                    density: Fluid::WATER_DENSITY,
+                    linear_drag: 1.0,
+                    angular_drag: 1.0,
+                });
+            }
+        });
+    }
+
+    fn draw_selected_ui(&mut self, ui: &mut Ui) {
+        let Some(object) = self.selected.and_then(|h| self.world.get_mut(h))
+        else {
+            ui.label("Click an object to edit it");
+            return;
+        };
+        ui.label(format!("Selected object mass: {:.3}", object.mass()));
+        let mut material = *object.material();
+        ui.horizontal(|ui| {
+            ui.label("Material: ");
+            for (preset, name) in [
+                (Material::ICE, "Ice"),
+                (Material::RUBBER, "Rubber"),
+                (Material::STEEL, "Steel"),
+            ] {
+                if ui.button(name).clicked() {
+                    material = preset;
+                }
+            }
+        });
+        ui.add(
+            DragValue::new(&mut material.restitution)
+                .prefix("Restitution: ")
+                .clamp_range(0.0..=1.0)
+                .speed(0.005),
+        );
+        ui.add(
+            DragValue::new(&mut material.static_friction)
+                .prefix("Static friction: ")
+                .clamp_range(0.0..=2.0)
+                .speed(0.005),
+        );
+        ui.add(
+            DragValue::new(&mut material.dynamic_friction)
+                .prefix("Dynamic friction: ")
+                .clamp_range(0.0..=2.0)
+                .speed(0.005),
+        );
+        ui.add(
+            DragValue::new(&mut material.rolling_friction)
+                .prefix("Rolling friction: ")
+                .clamp_range(0.0..=1.0)
+                .speed(0.001),
+        );
+        ui.add(
+            DragValue::new(&mut material.density)
+                .prefix("Density: ")
+                .clamp_range(0.01..=100.0)
+                .speed(0.01),
+        );
+        if material != *object.material() {
+            object.set_material(material);
+        }
+        ui.add(
+            DragValue::new(&mut object.collision_layer)
+                .prefix("Collision layer: ")
+                .hexadecimal(8, false, true),
+        );
+        ui.add(
+            DragValue::new(&mut object.collision_mask)
+                .prefix("Collision mask: ")
+                .hexadecimal(8, false, true),
+        );
+        let mut own_damping = object.damping.is_some();
+        if ui.checkbox(&mut own_damping, "Own damping").changed() {
+            object.damping = own_damping.then_some(Damping::NONE);
+        }
+        if let Some(damping) = &mut object.damping {
+            damping_drag_values(ui, damping);
+        }
+    }
+
+    /// How far the drawn frame is between the last two steps.
+    fn render_alpha(&self) -> f64 {
+        if self.paused {
+            1.0
+        } else {
+            self.timestep.alpha()
+        }
+    }
+
     fn shadow_camera(&self) -> &FirstPersonCamera {
         self.frozen_camera.as_ref().unwrap_or(&self.camera)
     }
 }
 
+fn combine_rule_radio(ui: &mut Ui, label: &str, rule: &mut CombineRule) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        for option in [
+            CombineRule::Average,
+            CombineRule::Min,
+            CombineRule::Max,
+            CombineRule::Multiply,
+        ] {
+            ui.radio_value(rule, option, option.name());
+        }
+    });
+}
+
+fn integrator_radio(ui: &mut Ui, integrator: &mut Integrator) {
+    ui.horizontal(|ui| {
+        ui.label("Integrator: ");
+        for option in [
+            Integrator::SymplecticEuler,
+            Integrator::VelocityVerlet,
+            Integrator::RungeKutta4,
+            Integrator::ImplicitGyroscopic,
+        ] {
+            ui.radio_value(integrator, option, option.name());
+        }
+    });
+}
+
+fn damping_drag_values(ui: &mut Ui, damping: &mut Damping) {
+    ui.horizontal(|ui| {
+        ui.add(
+            DragValue::new(&mut damping.linear)
+                .prefix("Linear damping: ")
+                .clamp_range(0.0..=10.0)
+                .speed(0.01),
+        );
+        ui.add(
+            DragValue::new(&mut damping.angular)
+                .prefix("Angular damping: ")
+                .clamp_range(0.0..=10.0)
+                .speed(0.01),
+        );
+    });
+}
+
+fn aerodynamics_ui(ui: &mut Ui, aerodynamics: &mut Option<Aerodynamics>) {
+    let mut enabled = aerodynamics.is_some();
+    if ui.checkbox(&mut enabled, "Aerodynamics").changed() {
+        *aerodynamics = enabled.then(Aerodynamics::default);
+    }
+    let Some(aerodynamics) = aerodynamics else {
+        return;
+    };
+    ui.add(
+        DragValue::new(&mut aerodynamics.air_density)
+            .prefix("Air density: ")
+            .clamp_range(0.0..=f64::INFINITY)
+            .speed(0.0001),
+    );
+    ui.horizontal(|ui| {
+        ui.add(
+            DragValue::new(&mut aerodynamics.drag_coefficient)
+                .prefix("Drag coefficient: ")
+                .clamp_range(0.0..=2.0)
+                .speed(0.01),
+        );
+        ui.add(
+            DragValue::new(&mut aerodynamics.lift_coefficient)
+                .prefix("Lift coefficient: ")
+                .clamp_range(0.0..=2.0)
+                .speed(0.01),
+        );
+    });
+}
+
+fn vector_drag_value(
+    ui: &mut Ui,
+    label: &str,
+    vector: &mut Vector3<f64>,
+    speed: f64,
+) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        ui.add(DragValue::new(&mut vector.x).prefix("x: ").speed(speed));
+        ui.add(DragValue::new(&mut vector.y).prefix("y: ").speed(speed));
+        ui.add(DragValue::new(&mut vector.z).prefix("z: ").speed(speed));
+    });
+}
+
 impl Scene for MainScene {
     fn draw(&mut self, ctx: &mut Context, delta: f64) {
         if self.camera.focus() {
@@ -714,19 +1580,14 @@ impl Scene for MainScene {
     }
 
     fn update(&mut self, delta: f64) {
-        const TICK_RATE_TARGET: f64 = 100.0;
-        const MAX_STEP_COUNT: u32 = 10;
         self.camera.update(delta as f32);
-        #[allow(clippy::cast_sign_loss)]
-        let step_count =
-            MAX_STEP_COUNT.min((delta * TICK_RATE_TARGET).abs().ceil() as u32);
-        let step_size =
-            f64::min(delta / f64::from(step_count), 1.0 / TICK_RATE_TARGET);
+        let step_count = self.timestep.advance(delta);
+        let step_size = self.timestep.step_size;
         if !self.paused {
             for _ in 0..step_count {
-                self.simulation.simulate(&mut self.objects, step_size);
+                self.simulation.simulate(&mut self.world, step_size);
                 if self.record {
-                    self.recording.save_frame_from(&self.objects);
+                    self.recording.save_frame_from(&self.world);
                 }
             }
             self.selected_frame = self.recording.last_frame_index();
@@ -736,7 +1597,7 @@ impl Scene for MainScene {
                 self.selected_frame %= self.recording.frame_count();
             }
             self.recording
-                .load_frame_to(self.selected_frame, &mut self.objects);
+                .load_frame_to(self.selected_frame, &mut self.world);
         }
     }
 
@@ -751,17 +1612,9 @@ impl Scene for MainScene {
                     ..
                 } if self.camera.focus() => {
                     let ray = self.camera.get_ray();
-                    if let Some((t, o)) = self
-                        .objects
-                        .iter_mut()
-                        .filter_map(|o| {
-                            o.collider
-                                .check_ray_hit(o.position, o.rotation, &ray)
-                                .map(|h| (h, o))
-                        })
-                        .min_by(|(h1, _), (h2, _)| h1.total_cmp(h2))
-                    {
-                        o.apply_impulse(
+                    if let Some((handle, t)) = self.world.cast_ray(&ray) {
+                        self.selected = Some(handle);
+                        self.world[handle].apply_impulse(
                             ray.start + ray.direction * t,
                             2.0 * ray.direction,
                         );
@@ -0,0 +1,257 @@
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+use smallvec::SmallVec;
+
+pub const MAX_MANIFOLD_POINTS: usize = 4;
+
+pub type Manifold = SmallVec<[ManifoldPoint; MAX_MANIFOLD_POINTS]>;
+
+/// Clipping a quad with a quad can produce at most 8 points.
+type Polygon = SmallVec<[Point3<f64>; 8]>;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ManifoldPoint {
+    /// The contact point on the first and on the second shape.
+    pub points: (Point3<f64>, Point3<f64>),
+    /// How deep the shapes overlap at this point along the contact normal.
+    pub depth: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OrientedBox {
+    pub center: Point3<f64>,
+    pub rotation: UnitQuaternion<f64>,
+    pub half_size: Vector3<f64>,
+}
+
+#[derive(Debug)]
+struct Face {
+    center: Point3<f64>,
+    normal: Vector3<f64>,
+    /// The in-plane axes of the face with their half sizes.
+    tangents: [(Vector3<f64>, f64); 2],
+}
+
+impl OrientedBox {
+    #[must_use]
+    pub fn new(
+        center: Point3<f64>,
+        rotation: UnitQuaternion<f64>,
+        size: Vector3<f64>,
+    ) -> Self {
+        Self {
+            center,
+            rotation,
+            half_size: size / 2.0,
+        }
+    }
+
+    fn axis(&self, i: usize) -> Vector3<f64> {
+        self.rotation * Vector3::ith(i, 1.0)
+    }
+
+    /// Finds the face whose outward normal is the closest to the direction.
+    /// Also returns how well the normal aligns with the direction.
+    fn best_face(&self, direction: &Vector3<f64>) -> (Face, f64) {
+        let (i, alignment) = (0..3)
+            .map(|i| (i, self.axis(i).dot(direction)))
+            .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+            .expect("a box has three axes");
+        let normal = self.axis(i) * alignment.signum();
+        let j = (i + 1) % 3;
+        let k = (i + 2) % 3;
+        let face = Face {
+            center: self.center + normal * self.half_size[i],
+            normal,
+            tangents: [
+                (self.axis(j), self.half_size[j]),
+                (self.axis(k), self.half_size[k]),
+            ],
+        };
+        (face, alignment.abs())
+    }
+}
+
+impl Face {
+    fn vertices(&self) -> [Point3<f64>; 4] {
+        let [(t1, h1), (t2, h2)] = self.tangents;
+        [
+            self.center + t1 * h1 + t2 * h2,
+            self.center - t1 * h1 + t2 * h2,
+            self.center - t1 * h1 - t2 * h2,
+            self.center + t1 * h1 - t2 * h2,
+        ]
+    }
+}
+
+/// Generates the contact manifold of two overlapping boxes.
+///
+/// The normal has to point from `b` towards `a`, like the normal returned by
+/// [`crate::gjk::gjk`]. The face of one box that is the most aligned with the
+/// normal is used as the reference face, the face of the other box facing it
+/// is clipped against the sides of the reference face. The points under the
+/// reference face make up the manifold.
+#[must_use]
+pub fn box_box(
+    a: &OrientedBox,
+    b: &OrientedBox,
+    normal: &Vector3<f64>,
+) -> Manifold {
+    let (face_a, alignment_a) = a.best_face(&-normal);
+    let (face_b, alignment_b) = b.best_face(normal);
+    // `a` is preferred when the faces are almost equally aligned,
+    // so the reference face does not flip between frames
+    let flipped = alignment_b > alignment_a.mul_add(1.02, 0.001);
+    let (reference, incident_box) =
+        if flipped { (face_b, a) } else { (face_a, b) };
+    let (incident, _) = incident_box.best_face(&-reference.normal);
+
+    let mut polygon: Polygon = incident.vertices().into_iter().collect();
+    for (tangent, half_size) in reference.tangents {
+        for side in [tangent, -tangent] {
+            let offset = side.dot(&reference.center.coords) + half_size;
+            polygon = clip(&polygon, &side, offset);
+        }
+    }
+
+    let points: Manifold = polygon
+        .iter()
+        .filter_map(|p| {
+            let separation = (p - reference.center).dot(&reference.normal);
+            (separation <= 0.0).then(|| {
+                let on_reference = p - reference.normal * separation;
+                ManifoldPoint {
+                    points: if flipped {
+                        (*p, on_reference)
+                    } else {
+                        (on_reference, *p)
+                    },
+                    depth: -separation,
+                }
+            })
+        })
+        .collect();
+    reduce(points)
+}
+
+/// Sutherland–Hodgman clipping, keeps the part of the polygon where
+/// `normal.dot(p) <= offset`.
+fn clip(polygon: &Polygon, normal: &Vector3<f64>, offset: f64) -> Polygon {
+    let mut clipped = Polygon::new();
+    for (i, start) in polygon.iter().enumerate() {
+        let end = &polygon[(i + 1) % polygon.len()];
+        let start_distance = normal.dot(&start.coords) - offset;
+        let end_distance = normal.dot(&end.coords) - offset;
+        if start_distance <= 0.0 {
+            clipped.push(*start);
+        }
+        if (start_distance <= 0.0) != (end_distance <= 0.0) {
+            let t = start_distance / (start_distance - end_distance);
+            clipped.push(start + (end - start) * t);
+        }
+    }
+    clipped
+}
+
+/// Keeps at most four points: the deepest one, the one furthest from it,
+/// and the two that maximize the covered area.
+fn reduce(mut points: Manifold) -> Manifold {
+    if points.len() <= MAX_MANIFOLD_POINTS {
+        return points;
+    }
+    let mut reduced = Manifold::new();
+    let take_best = |points: &mut Manifold,
+                     score: &dyn Fn(&Point3<f64>) -> f64| {
+        let index = points
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| {
+                score(&a.points.0).total_cmp(&score(&b.points.0))
+            })
+            .map(|(i, _)| i)
+            .expect("there are more points than the maximum");
+        points.swap_remove(index)
+    };
+
+    let deepest = points
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.depth.total_cmp(&b.depth))
+        .map(|(i, _)| i)
+        .expect("there are more points than the maximum");
+    reduced.push(points.swap_remove(deepest));
+    let first = reduced[0].points.0;
+    reduced.push(take_best(&mut points, &|p| (p - first).magnitude_squared()));
+    let second = reduced[1].points.0;
+    reduced.push(take_best(&mut points, &|p| {
+        (p - first).cross(&(p - second)).magnitude_squared()
+    }));
+    let third = reduced[2].points.0;
+    reduced.push(take_best(&mut points, &|p| {
+        (p - first).cross(&(p - second)).magnitude()
+            + (p - second).cross(&(p - third)).magnitude()
+            + (p - third).cross(&(p - first)).magnitude()
+    }));
+    reduced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn box_resting_on_box_has_four_points() {
+        let ground = OrientedBox::new(
+            Point3::new(0.0, -5.0, 0.0),
+            UnitQuaternion::identity(),
+            Vector3::new(100.0, 10.0, 100.0),
+        );
+        let cube = OrientedBox::new(
+            Point3::new(0.0, 0.49, 0.0),
+            UnitQuaternion::identity(),
+            Vector3::new(1.0, 1.0, 1.0),
+        );
+        let manifold = box_box(&cube, &ground, &Vector3::y());
+        assert_eq!(manifold.len(), 4);
+        for point in manifold {
+            assert!((point.depth - 0.01).abs() < 1e-9);
+            assert!((point.points.0.y - -0.01).abs() < 1e-9);
+            assert!(point.points.1.y.abs() < 1e-9);
+            assert!((point.points.0.x.abs() - 0.5).abs() < 1e-9);
+            assert!((point.points.0.z.abs() - 0.5).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn rotated_boxes_are_reduced_to_four_points() {
+        let bottom = OrientedBox::new(
+            Point3::new(0.0, -0.5, 0.0),
+            UnitQuaternion::identity(),
+            Vector3::new(1.0, 1.0, 1.0),
+        );
+        let top = OrientedBox::new(
+            Point3::new(0.0, 0.49, 0.0),
+            UnitQuaternion::new(Vector3::y() * std::f64::consts::FRAC_PI_4),
+            Vector3::new(1.0, 1.0, 1.0),
+        );
+        let manifold = box_box(&top, &bottom, &Vector3::y());
+        assert_eq!(manifold.len(), MAX_MANIFOLD_POINTS);
+        for point in manifold {
+            assert!((point.depth - 0.01).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn clip_keeps_inside_part() {
+        let polygon: Polygon = [
+            Point3::new(-1.0, 0.0, -1.0),
+            Point3::new(1.0, 0.0, -1.0),
+            Point3::new(1.0, 0.0, 1.0),
+            Point3::new(-1.0, 0.0, 1.0),
+        ]
+        .into_iter()
+        .collect();
+        let clipped = clip(&polygon, &Vector3::x(), 0.5);
+        assert_eq!(clipped.len(), 4);
+        assert!(clipped.iter().all(|p| p.x <= 0.5));
+    }
+}
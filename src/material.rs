@@ -0,0 +1,122 @@
+/// The physical properties of an object's surface and body.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Material {
+    /// How much of the approaching velocity is kept after a collision.
+    pub restitution: f64,
+    /// The friction coefficient of contacts that do not slide.
+    pub static_friction: f64,
+    /// The friction coefficient of sliding contacts.
+    pub dynamic_friction: f64,
+    /// The resistance against rolling, the angular impulse stopping the
+    /// rolling is at most this times the normal impulse.
+    pub rolling_friction: f64,
+    /// Mass per unit volume.
+    pub density: f64,
+}
+
+impl Material {
+    pub const DEFAULT: Self = Self {
+        restitution: 1.0,
+        static_friction: 1.0,
+        dynamic_friction: 1.0,
+        rolling_friction: 0.0,
+        density: 1.0,
+    };
+
+    pub const ICE: Self = Self {
+        restitution: 0.1,
+        static_friction: 0.1,
+        dynamic_friction: 0.03,
+        rolling_friction: 0.0,
+        density: 0.92,
+    };
+
+    pub const RUBBER: Self = Self {
+        restitution: 0.85,
+        static_friction: 1.0,
+        dynamic_friction: 0.8,
+        rolling_friction: 0.05,
+        density: 1.1,
+    };
+
+    pub const STEEL: Self = Self {
+        restitution: 0.6,
+        static_friction: 0.75,
+        dynamic_friction: 0.55,
+        rolling_friction: 0.01,
+        density: 7.8,
+    };
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// How the coefficients of two materials are combined
+/// into the coefficient of their contact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CombineRule {
+    Average,
+    Min,
+    Max,
+    Multiply,
+}
+
+impl CombineRule {
+    #[must_use]
+    pub fn combine(self, a: f64, b: f64) -> f64 {
+        match self {
+            Self::Average => a.midpoint(b),
+            Self::Min => a.min(b),
+            Self::Max => a.max(b),
+            Self::Multiply => a * b,
+        }
+    }
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Average => "Average",
+            Self::Min => "Min",
+            Self::Max => "Max",
+            Self::Multiply => "Multiply",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combine_rules() {
+        let (a, b) = (Material::ICE, Material::RUBBER);
+        let combine = |rule: CombineRule| {
+            rule.combine(a.dynamic_friction, b.dynamic_friction)
+        };
+        assert!((combine(CombineRule::Average) - 0.415).abs() < 1e-12);
+        assert!((combine(CombineRule::Min) - 0.03).abs() < 1e-12);
+        assert!((combine(CombineRule::Max) - 0.8).abs() < 1e-12);
+        assert!((combine(CombineRule::Multiply) - 0.024).abs() < 1e-12);
+    }
+
+    #[test]
+    fn combining_is_symmetric() {
+        for rule in [
+            CombineRule::Average,
+            CombineRule::Min,
+            CombineRule::Max,
+            CombineRule::Multiply,
+        ] {
+            let difference = rule.combine(0.2, 0.7) - rule.combine(0.7, 0.2);
+            assert!(difference.abs() < 1e-12);
+        }
+        // only multiplying changes the coefficient of the same materials
+        for rule in [CombineRule::Average, CombineRule::Min, CombineRule::Max] {
+            assert!((rule.combine(0.4, 0.4) - 0.4).abs() < 1e-12);
+        }
+        assert!((CombineRule::Multiply.combine(0.4, 0.4) - 0.16).abs() < 1e-12);
+    }
+}
@@ -3,7 +3,9 @@ use std::{error::Error, marker::PhantomData};
 
 use glow::{HasContext, NativeVertexArray};
 
-use crate::{context::Context, render_state::RenderState, vertex::Vertex};
+#[cfg(feature = "ui")]
+use crate::context::Context;
+use crate::{render_state::RenderState, vertex::Vertex};
 
 #[derive(Clone, Copy, Debug)]
 #[allow(clippy::cast_possible_wrap)]
@@ -31,7 +33,7 @@ pub struct Mesh<V: Vertex> {
 
 impl<V: Vertex> Mesh<V> {
     pub fn new(
-        ctx: &Context,
+        gl: &glow::Context,
         vertices: &[V],
         indices: &[u16],
         primitive: MeshPrimitive,
@@ -45,7 +47,6 @@ impl<V: Vertex> Mesh<V> {
             }
         }
         let vertex_array = unsafe {
-            let gl = &ctx.gl;
             let vertex_array = gl.create_vertex_array().map_err(MeshError)?;
             let vertex_buffer = gl.create_buffer().map_err(MeshError)?;
             let index_buffer = gl.create_buffer().map_err(MeshError)?;
@@ -95,6 +96,7 @@ pub trait DrawMesh {
     unsafe fn draw_mesh<V: Vertex>(&mut self, mesh: &Mesh<V>);
 }
 
+#[cfg(feature = "ui")]
 impl DrawMesh for Context {
     unsafe fn draw_mesh<V: Vertex>(&mut self, mesh: &Mesh<V>) {
         unsafe {
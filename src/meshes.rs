@@ -3,12 +3,41 @@ use std::f32::consts::PI;
 
 use anyhow::Result;
 
-use crate::context::Context;
+use crate::collider::Collider;
 use crate::mesh::{Mesh, MeshPrimitive};
 use crate::vertex::{PNVertex, PVertex};
 
+/// The meshes objects are drawn with, a unit mesh for each collider shape
+/// that is scaled by [`Object::mesh_scale`](crate::object::Object::mesh_scale).
+#[derive(Debug)]
+pub struct ColliderMeshes {
+    box_mesh: Mesh<PNVertex>,
+    sphere_mesh: Mesh<PNVertex>,
+}
+
+impl ColliderMeshes {
+    pub fn new(gl: &glow::Context) -> Result<Self> {
+        Ok(Self {
+            box_mesh: box_mesh(gl)?,
+            // sphere_mesh: sphere_mesh(gl, 16, false)?,
+            sphere_mesh: icosphere_mesh(gl, 2)?,
+        })
+    }
+
+    /// The mesh of a sphere or a box, compounds are drawn part by part
+    /// with [`Object::interpolated_models`](crate::object::Object::interpolated_models).
+    #[must_use]
+    pub const fn get(&self, collider: &Collider) -> Option<&Mesh<PNVertex>> {
+        match collider {
+            Collider::Sphere(_) => Some(&self.sphere_mesh),
+            Collider::Box(..) => Some(&self.box_mesh),
+            Collider::Compound(_) => None,
+        }
+    }
+}
+
 #[rustfmt::skip]
-pub fn box_mesh(ctx: &Context) -> Result<Mesh<PNVertex>> {
+pub fn box_mesh(gl: &glow::Context) -> Result<Mesh<PNVertex>> {
     let vertices = [
        PNVertex { position: [ 0.5,  0.5,  0.5], normal: [ 0.0,  0.0,  1.0,] },
        PNVertex { position: [-0.5,  0.5,  0.5], normal: [ 0.0,  0.0,  1.0,] },
@@ -48,11 +77,11 @@ pub fn box_mesh(ctx: &Context) -> Result<Mesh<PNVertex>> {
         16, 17, 18, 18, 17, 19,
         20, 21, 22, 22, 21, 23,
     ];
-    Mesh::new(ctx, &vertices, &indices, MeshPrimitive::Triangles)
+    Mesh::new(gl, &vertices, &indices, MeshPrimitive::Triangles)
 }
 
 pub fn sphere_mesh(
-    ctx: &Context,
+    gl: &glow::Context,
     resolution: u16,
     half_triangles: bool,
 ) -> Result<Mesh<PNVertex>> {
@@ -101,11 +130,11 @@ pub fn sphere_mesh(
             }
         }
     }
-    Mesh::new(ctx, &vertices, &indices, MeshPrimitive::Triangles)
+    Mesh::new(gl, &vertices, &indices, MeshPrimitive::Triangles)
 }
 
 #[rustfmt::skip]
-pub fn bounding_box_mesh(ctx: &Context) -> Result<Mesh<PVertex>> {
+pub fn bounding_box_mesh(gl: &glow::Context) -> Result<Mesh<PVertex>> {
     let vertices = [
        PVertex { position: [ 0.5,  0.5,  0.5] },
        PVertex { position: [-0.5,  0.5,  0.5] },
@@ -122,11 +151,11 @@ pub fn bounding_box_mesh(ctx: &Context) -> Result<Mesh<PVertex>> {
         4, 5, 5, 7, 7, 6, 6, 4,
         0, 5, 1, 4, 3, 6, 2, 7,
     ];
-    Mesh::new(ctx, &vertices, &indices, MeshPrimitive::Lines)
+    Mesh::new(gl, &vertices, &indices, MeshPrimitive::Lines)
 }
 
 #[rustfmt::skip]
-pub fn rectangle_mesh(ctx: &Context) -> Result<Mesh<PVertex>> {
+pub fn rectangle_mesh(gl: &glow::Context) -> Result<Mesh<PVertex>> {
     let vertices = [
        PVertex { position: [ 0.5,  0.5,  0.0] },
        PVertex { position: [-0.5,  0.5,  0.0] },
@@ -136,7 +165,7 @@ pub fn rectangle_mesh(ctx: &Context) -> Result<Mesh<PVertex>> {
     let indices = [
         0, 1, 2, 2, 1, 3
     ];
-    Mesh::new(ctx, &vertices, &indices, MeshPrimitive::Triangles)
+    Mesh::new(gl, &vertices, &indices, MeshPrimitive::Triangles)
 }
 
 struct Polyhedron {
@@ -270,7 +299,7 @@ fn icosphere(subdivisions: usize) -> Polyhedron {
 }
 
 pub fn icosphere_mesh(
-    ctx: &Context,
+    gl: &glow::Context,
     subdivisions: usize,
 ) -> Result<Mesh<PNVertex>> {
     let Polyhedron { vertices, faces } = icosphere(subdivisions);
@@ -285,5 +314,5 @@ pub fn icosphere_mesh(
         .into_iter()
         .flat_map(|f| f.map(|p| p as u16))
         .collect();
-    Mesh::new(ctx, &vertices, &indices, MeshPrimitive::Triangles)
+    Mesh::new(gl, &vertices, &indices, MeshPrimitive::Triangles)
 }
@@ -1,60 +1,402 @@
-use std::rc::Rc;
-
 use nalgebra::{
-    Matrix3, Matrix4, Point3, Rotation3, Scale3, Translation3, Vector3,
+    Matrix3, Matrix4, Point3, Quaternion, Scale3, Translation3, UnitQuaternion,
+    Vector3,
+};
+use smallvec::SmallVec;
+
+use crate::{
+    aabb::AABB,
+    ccd::Motion,
+    collider::Collider,
+    kinematic::{Kinematic, KinematicMotion},
+    material::Material,
 };
 
-use crate::{aabb::AABB, collider::Collider, mesh::Mesh, vertex::PNVertex};
+/// Slows down the movement of an object, the coefficients are the fraction
+/// of the velocity lost per second at small values.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Damping {
+    pub linear: f64,
+    pub angular: f64,
+}
+
+impl Damping {
+    pub const NONE: Self = Self {
+        linear: 0.0,
+        angular: 0.0,
+    };
+}
+
+/// The derivatives of the position, momentum and rotation.
+type Derivative = (Vector3<f64>, Vector3<f64>, Quaternion<f64>);
 
 #[derive(Debug)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Object {
-    pub mesh: Rc<Mesh<PNVertex>>,
-    // HACK collider should be private
-    // because changing the collider changes the inverse inertia
-    pub collider: Collider,
-    pub position: Point3<f64>,
-    pub rotation: Rotation3<f64>,
+    collider: Collider,
+    position: Point3<f64>,
+    rotation: UnitQuaternion<f64>,
     pub immovable: bool,
     pub momentum: Vector3<f64>,
     pub angular_momentum: Vector3<f64>,
-    // HACK mass should be private
-    // because changing the mass changes the inverse inertia
-    pub mass: f64,
-    // HACK inverse_body inertia should be private
-    // because it is inferred from collider and mass
-    pub inverse_body_inertia: Matrix3<f64>,
-    pub mesh_scale: Vector3<f32>,
-    pub aabb: AABB,
+    mass: f64,
+    inverse_body_inertia: Matrix3<f64>,
+    /// Follows the pose, which is only changed through methods.
+    aabb: AABB,
+    pub sleeping: bool,
+    /// How long the object has been slow enough to fall asleep.
+    pub sleep_timer: f64,
+    /// The island the object fell asleep in, waking the object
+    /// wakes the whole island.
+    pub island: Option<u64>,
+    /// Enables continuous collision detection, so the object cannot tunnel
+    /// through other objects when it moves fast.
+    pub ccd: bool,
+    material: Material,
+    /// The collision layers the object is on.
+    pub collision_layer: u32,
+    /// The collision layers the object collides with.
+    pub collision_mask: u32,
+    /// Sensors report the objects overlapping them,
+    /// but they do not collide with anything.
+    pub sensor: bool,
+    /// Kinematic objects are moved by user code instead of forces.
+    pub kinematic: Option<Kinematic>,
+    /// `None` uses the damping of the simulation.
+    pub damping: Option<Damping>,
+    /// The pose before the last step, used for interpolating between
+    /// the steps when drawing. `None` if the object was just placed.
+    pub previous_pose: Option<(Point3<f64>, UnitQuaternion<f64>)>,
 }
 
-impl Object {
-    #[must_use]
-    pub fn new(
-        mesh: &Rc<Mesh<PNVertex>>,
-        collider: Collider,
-        mass: f64,
-    ) -> Self {
+/// Creates an [`Object`] whose mass, inertia and bounds are derived
+/// from its collider.
+///
+/// The mass is calculated from the density of the material,
+/// unless it is set explicitly.
+#[derive(Debug)]
+#[allow(clippy::struct_excessive_bools)]
+#[must_use]
+pub struct ObjectBuilder {
+    collider: Collider,
+    material: Material,
+    mass: Option<f64>,
+    position: Point3<f64>,
+    rotation: UnitQuaternion<f64>,
+    velocity: Vector3<f64>,
+    angular_velocity: Vector3<f64>,
+    immovable: bool,
+    ccd: bool,
+    sensor: bool,
+    collision_layer: u32,
+    collision_mask: u32,
+    kinematic: Option<Kinematic>,
+    damping: Option<Damping>,
+}
+
+impl ObjectBuilder {
+    pub const fn new(collider: Collider) -> Self {
         Self {
-            mesh: mesh.clone(),
+            collider,
+            material: Material::DEFAULT,
+            mass: None,
             position: Point3::new(0.0, 0.0, 0.0),
-            rotation: Rotation3::identity(),
+            rotation: UnitQuaternion::new_unchecked(Quaternion::new(
+                1.0, 0.0, 0.0, 0.0,
+            )),
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+            angular_velocity: Vector3::new(0.0, 0.0, 0.0),
             immovable: false,
-            momentum: Vector3::zeros(),
-            angular_momentum: Vector3::new(0.0, 0.0, 0.0),
-            mass,
-            inverse_body_inertia: collider.inverse_inertia(mass),
+            ccd: false,
+            sensor: false,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            kinematic: None,
+            damping: None,
+        }
+    }
+
+    /// Sets the material, the mass is calculated from its density.
+    pub const fn material(mut self, material: Material) -> Self {
+        self.material = material;
+        self.mass = None;
+        self
+    }
+
+    /// Sets the density of the material, the mass is calculated from it.
+    pub const fn density(mut self, density: f64) -> Self {
+        self.material.density = density;
+        self.mass = None;
+        self
+    }
+
+    /// Sets the mass, the density of the material is calculated from it.
+    pub const fn mass(mut self, mass: f64) -> Self {
+        self.mass = Some(mass);
+        self
+    }
+
+    pub const fn position(mut self, position: Point3<f64>) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub const fn rotation(mut self, rotation: UnitQuaternion<f64>) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Sets the initial velocity, the momentum is calculated from it.
+    pub const fn velocity(mut self, velocity: Vector3<f64>) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    /// Sets the initial angular velocity,
+    /// the angular momentum is calculated from it.
+    pub const fn angular_velocity(
+        mut self,
+        angular_velocity: Vector3<f64>,
+    ) -> Self {
+        self.angular_velocity = angular_velocity;
+        self
+    }
+
+    pub const fn immovable(mut self) -> Self {
+        self.immovable = true;
+        self
+    }
+
+    /// Enables continuous collision detection.
+    pub const fn ccd(mut self) -> Self {
+        self.ccd = true;
+        self
+    }
+
+    pub const fn sensor(mut self) -> Self {
+        self.sensor = true;
+        self
+    }
+
+    /// Sets the collision layers the object is on
+    /// and the layers it collides with.
+    pub const fn collision_layers(mut self, layer: u32, mask: u32) -> Self {
+        self.collision_layer = layer;
+        self.collision_mask = mask;
+        self
+    }
+
+    pub fn kinematic(mut self, motion: KinematicMotion) -> Self {
+        self.kinematic = Some(Kinematic::new(motion));
+        self
+    }
+
+    /// Overrides the damping of the simulation for this object.
+    pub const fn damping(mut self, damping: Damping) -> Self {
+        self.damping = Some(damping);
+        self
+    }
+
+    /// Builds the object, a compound collider is moved so its center of
+    /// mass is at the position of the object, and the position is moved
+    /// with it, so the parts stay where they were placed.
+    #[must_use]
+    pub fn build(self) -> Object {
+        let (collider, center) = self.collider.centered();
+        let position = self.position + self.rotation * center;
+        let mut object = Object {
+            aabb: collider.aabb(&position, &self.rotation),
             collider,
-            mesh_scale: Vector3::new(1.0, 1.0, 1.0),
-            aabb: AABB::new(Point3::default(), Point3::default()),
+            position,
+            rotation: self.rotation,
+            immovable: self.immovable,
+            momentum: Vector3::zeros(),
+            angular_momentum: Vector3::zeros(),
+            mass: 0.0,
+            inverse_body_inertia: Matrix3::zeros(),
+            sleeping: false,
+            sleep_timer: 0.0,
+            island: None,
+            ccd: self.ccd,
+            material: self.material,
+            collision_layer: self.collision_layer,
+            collision_mask: self.collision_mask,
+            sensor: self.sensor,
+            kinematic: self.kinematic,
+            damping: self.damping,
+            previous_pose: None,
+        };
+        match self.mass {
+            Some(mass) => object.set_mass(mass),
+            None => object.set_density(self.material.density),
         }
+        object.momentum = self.velocity * object.mass;
+        object.angular_momentum = object
+            .inverse_inertia()
+            .try_inverse()
+            .unwrap_or_else(Matrix3::zeros)
+            * self.angular_velocity;
+        object
+    }
+}
+
+impl Object {
+    #[must_use]
+    pub const fn collider(&self) -> &Collider {
+        &self.collider
+    }
+
+    #[must_use]
+    pub const fn position(&self) -> Point3<f64> {
+        self.position
+    }
+
+    #[must_use]
+    pub const fn rotation(&self) -> UnitQuaternion<f64> {
+        self.rotation
+    }
+
+    /// Places the object at the pose, its bounds follow it.
+    pub fn set_pose(
+        &mut self,
+        position: Point3<f64>,
+        rotation: UnitQuaternion<f64>,
+    ) {
+        self.position = position;
+        self.rotation = rotation;
+        self.update_aabb();
+    }
+
+    pub fn set_position(&mut self, position: Point3<f64>) {
+        self.set_pose(position, self.rotation);
+    }
+
+    pub fn set_rotation(&mut self, rotation: UnitQuaternion<f64>) {
+        self.set_pose(self.position, rotation);
+    }
+
+    fn update_aabb(&mut self) {
+        self.aabb = self.collider.aabb(&self.position, &self.rotation);
+    }
+
+    #[must_use]
+    pub const fn mass(&self) -> f64 {
+        self.mass
+    }
+
+    #[must_use]
+    pub const fn inverse_body_inertia(&self) -> &Matrix3<f64> {
+        &self.inverse_body_inertia
+    }
+
+    #[must_use]
+    pub const fn material(&self) -> &Material {
+        &self.material
+    }
+
+    /// Changes the shape of the object, the density is kept,
+    /// so the mass changes with the volume.
+    /// A compound collider is centered like in [`ObjectBuilder::build`].
+    pub fn set_collider(&mut self, collider: Collider) {
+        let (collider, center) = collider.centered();
+        self.collider = collider;
+        self.position += self.rotation * center;
+        self.update_aabb();
+        self.set_density(self.material.density);
+    }
+
+    /// Sets the mass, the density of the material is calculated from it.
+    pub fn set_mass(&mut self, mass: f64) {
+        self.mass = mass;
+        self.material.density = mass / self.collider.volume();
+        self.inverse_body_inertia = self.collider.inverse_inertia(mass);
+    }
+
+    /// Sets the density of the material, the mass is calculated from it.
+    pub fn set_density(&mut self, density: f64) {
+        self.set_mass(density * self.collider.volume());
+    }
+
+    /// Sets the material of the object, the mass is calculated
+    /// from the density of the material.
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
+        self.set_density(material.density);
+    }
+
+    /// The scale of the unit mesh that matches the collider.
+    #[must_use]
+    pub fn mesh_scale(&self) -> Vector3<f32> {
+        self.collider.mesh_scale()
+    }
+
+    /// Whether the layers and masks of the objects allow them to collide,
+    /// both objects have to be on a layer the other one collides with.
+    #[must_use]
+    pub const fn can_collide(&self, other: &Self) -> bool {
+        self.collision_layer & other.collision_mask != 0
+            && other.collision_layer & self.collision_mask != 0
     }
 
     #[must_use]
     pub fn model(&self) -> Matrix4<f32> {
-        (Translation3::from(self.position.cast::<f32>())
-            * self.rotation.cast::<f32>())
-        .to_homogeneous()
-            * Scale3::from(self.mesh_scale).to_homogeneous()
+        self.model_at(&self.position, &self.rotation)
+    }
+
+    /// The model matrix between the previous and the current pose,
+    /// `alpha` is zero at the previous pose and one at the current one.
+    #[must_use]
+    pub fn interpolated_model(&self, alpha: f64) -> Matrix4<f32> {
+        let (position, rotation) = self.interpolated_pose(alpha);
+        self.model_at(&position, &rotation)
+    }
+
+    /// The model matrices of the parts of the object between the previous
+    /// and the current pose, with the colliders of the parts that select
+    /// their meshes. Spheres and boxes have a single part.
+    #[must_use]
+    pub fn interpolated_models(
+        &self,
+        alpha: f64,
+    ) -> SmallVec<[(Matrix4<f32>, Collider); 1]> {
+        let (position, rotation) = self.interpolated_pose(alpha);
+        self.collider
+            .leaves()
+            .map(|(leaf_position, leaf_rotation, leaf)| {
+                let model = model_matrix(
+                    &(position + rotation * leaf_position.coords),
+                    &(rotation * leaf_rotation),
+                    leaf.mesh_scale(),
+                );
+                (model, leaf)
+            })
+            .collect()
+    }
+
+    /// The pose between the previous and the current pose,
+    /// `alpha` is zero at the previous pose and one at the current one.
+    #[must_use]
+    pub fn interpolated_pose(
+        &self,
+        alpha: f64,
+    ) -> (Point3<f64>, UnitQuaternion<f64>) {
+        self.previous_pose.map_or(
+            (self.position, self.rotation),
+            |(position, rotation)| {
+                (
+                    position + (self.position - position) * alpha,
+                    rotation.slerp(&self.rotation, alpha),
+                )
+            },
+        )
+    }
+
+    fn model_at(
+        &self,
+        position: &Point3<f64>,
+        rotation: &UnitQuaternion<f64>,
+    ) -> Matrix4<f32> {
+        model_matrix(position, rotation, self.mesh_scale())
     }
 
     pub fn apply_impulse(
@@ -62,19 +404,197 @@ impl Object {
         attack_point: Point3<f64>,
         impulse: Vector3<f64>,
     ) {
-        if !self.immovable {
+        if self.is_dynamic() {
+            self.wake();
             self.momentum += impulse;
             self.angular_momentum +=
                 (attack_point - self.position).cross(&impulse);
         }
     }
 
-    pub fn update(&mut self, delta: f64) {
-        self.position += self.momentum * delta / self.mass;
-        self.rotation = Rotation3::new(
-            self.inverse_inertia() * self.angular_momentum * delta,
-        ) * self.rotation;
-        self.aabb = self.collider.aabb(&self.position, &self.rotation);
+    /// Applies an impulse that only changes the angular momentum.
+    pub fn apply_angular_impulse(&mut self, impulse: Vector3<f64>) {
+        if self.is_dynamic() {
+            self.wake();
+            self.angular_momentum += impulse;
+        }
+    }
+
+    /// Whether the object is moved by forces and impulses,
+    /// it is neither immovable nor kinematic.
+    #[must_use]
+    pub const fn is_dynamic(&self) -> bool {
+        !self.immovable && self.kinematic.is_none()
+    }
+
+    /// Whether the object is moved by the simulation,
+    /// it is dynamic and not sleeping.
+    #[must_use]
+    pub const fn is_active(&self) -> bool {
+        self.is_dynamic() && !self.sleeping
+    }
+
+    /// Whether the object can move in this step,
+    /// it is either active or kinematic.
+    #[must_use]
+    pub const fn is_moving(&self) -> bool {
+        self.is_active() || self.kinematic.is_some()
+    }
+
+    pub const fn wake(&mut self) {
+        if self.sleeping {
+            self.sleeping = false;
+            self.sleep_timer = 0.0;
+        }
+    }
+
+    /// Stops the object and puts it to sleep in the given island.
+    pub fn sleep(&mut self, island: u64) {
+        self.sleeping = true;
+        self.island = Some(island);
+        self.momentum = Vector3::zeros();
+        self.angular_momentum = Vector3::zeros();
+    }
+
+    /// Moves and rotates the object without changing its momentum.
+    pub fn displace(
+        &mut self,
+        translation: Vector3<f64>,
+        scaled_axis: Vector3<f64>,
+    ) {
+        if self.is_dynamic() {
+            self.position += translation;
+            self.rotation = UnitQuaternion::new(scaled_axis) * self.rotation;
+            self.rotation.renormalize_fast();
+            self.update_aabb();
+        }
+    }
+
+    /// Calculates the velocities of a kinematic object for the next step.
+    pub(crate) fn prepare_kinematic(&mut self, delta: f64) {
+        if let Some(kinematic) = &mut self.kinematic {
+            kinematic.prepare(&self.position, &self.rotation, delta);
+        }
+    }
+
+    /// Damps the momentum, then moves the object with its velocities.
+    /// `default_damping` is used if the object has no damping of its own.
+    pub fn update(&mut self, delta: f64, default_damping: Damping) {
+        self.damp(default_damping, delta);
+        self.position += self.velocity() * delta;
+        self.rotation = UnitQuaternion::new(self.angular_velocity() * delta)
+            * self.rotation;
+        self.rotation.renormalize_fast();
+        self.update_aabb();
+    }
+
+    /// Moves the object with the classic fourth order Runge-Kutta method,
+    /// `force` is the external force acting on the object at a position.
+    /// Unlike [`Object::update`] this also integrates the momentum.
+    pub fn update_rk4(
+        &mut self,
+        force: impl Fn(&Point3<f64>) -> Vector3<f64>,
+        delta: f64,
+        default_damping: Damping,
+    ) {
+        self.damp(default_damping, delta);
+        let (p0, m0, q0) =
+            (self.position, self.momentum, *self.rotation.quaternion());
+        let derivative = |k: &Derivative, h: f64| {
+            let position = p0 + k.0 * h;
+            let momentum = m0 + k.1 * h;
+            let rotation = UnitQuaternion::new_normalize(q0 + k.2 * h);
+            let angular_velocity =
+                self.inverse_inertia_at(&rotation) * self.angular_momentum;
+            (
+                momentum / self.mass,
+                force(&position),
+                Quaternion::from_imag(angular_velocity)
+                    * rotation.into_inner()
+                    * 0.5,
+            )
+        };
+        let zero = (Vector3::zeros(), Vector3::zeros(), Quaternion::default());
+        let k1 = derivative(&zero, 0.0);
+        let k2 = derivative(&k1, delta / 2.0);
+        let k3 = derivative(&k2, delta / 2.0);
+        let k4 = derivative(&k3, delta);
+        let h = delta / 6.0;
+        self.position = p0 + (k1.0 + k2.0 * 2.0 + k3.0 * 2.0 + k4.0) * h;
+        self.momentum = m0 + (k1.1 + k2.1 * 2.0 + k3.1 * 2.0 + k4.1) * h;
+        self.rotation = UnitQuaternion::new_normalize(
+            q0 + (k1.2 + k2.2 * 2.0 + k3.2 * 2.0 + k4.2) * h,
+        );
+        self.update_aabb();
+    }
+
+    /// Decays the momentum exponentially, so the result does not depend
+    /// on how the time is divided into steps.
+    fn damp(&mut self, default_damping: Damping, delta: f64) {
+        // the motion of immovable and kinematic objects is prescribed
+        if !self.is_dynamic() {
+            return;
+        }
+        let damping = self.damping.unwrap_or(default_damping);
+        self.momentum *= (-damping.linear * delta).exp();
+        self.angular_momentum *= (-damping.angular * delta).exp();
+    }
+
+    /// Moves the object like [`Object::update`], but rotates it with the
+    /// angular velocity at the end of the step, which is solved implicitly
+    /// with one Newton iteration in the body frame.
+    /// The angular momentum is kept, so fast spinning objects stay stable,
+    /// but they lose a bit of energy and drift towards their major axis.
+    pub fn update_gyroscopic(&mut self, delta: f64, default_damping: Damping) {
+        self.damp(default_damping, delta);
+        self.position += self.velocity() * delta;
+        let angular_velocity = self.gyroscopic_angular_velocity(delta);
+        self.rotation =
+            UnitQuaternion::new(angular_velocity * delta) * self.rotation;
+        self.rotation.renormalize_fast();
+        self.update_aabb();
+    }
+
+    /// The world space angular velocity at the end of the step,
+    /// when only the gyroscopic torque acts on the object.
+    fn gyroscopic_angular_velocity(&self, delta: f64) -> Vector3<f64> {
+        let angular_velocity = self.angular_velocity();
+        let Some(body_inertia) = self.inverse_body_inertia.try_inverse() else {
+            return angular_velocity;
+        };
+        let body_angular_velocity = self.rotation.inverse() * angular_velocity;
+        let angular_momentum = body_inertia * body_angular_velocity;
+        let residual = body_angular_velocity.cross(&angular_momentum) * delta;
+        let jacobian = body_inertia
+            + (body_angular_velocity.cross_matrix() * body_inertia
+                - angular_momentum.cross_matrix())
+                * delta;
+        jacobian
+            .try_inverse()
+            .map_or(angular_velocity, |inverse_jacobian| {
+                self.rotation
+                    * (body_angular_velocity - inverse_jacobian * residual)
+            })
+    }
+
+    /// The current pose and velocities of the object.
+    #[must_use]
+    pub fn motion(&self) -> Motion {
+        Motion {
+            position: self.position,
+            rotation: self.rotation,
+            velocity: self.velocity(),
+            angular_velocity: self.angular_velocity(),
+        }
+    }
+
+    /// The AABB that encloses the object during the whole next step.
+    #[must_use]
+    pub fn swept_aabb(&self, delta: f64) -> AABB {
+        let (position, rotation) = self.motion().at(delta);
+        self.collider
+            .aabb(&self.position, &self.rotation)
+            .merge(&self.collider.aabb(&position, &rotation))
     }
 
     #[must_use]
@@ -84,14 +604,70 @@ impl Object {
 
     #[must_use]
     pub fn inverse_inertia(&self) -> Matrix3<f64> {
-        self.rotation * self.inverse_body_inertia * self.rotation.inverse()
+        self.inverse_inertia_at(&self.rotation)
+    }
+
+    fn inverse_inertia_at(
+        &self,
+        rotation: &UnitQuaternion<f64>,
+    ) -> Matrix3<f64> {
+        let rotation = rotation.to_rotation_matrix();
+        rotation * self.inverse_body_inertia * rotation.inverse()
+    }
+
+    /// The inverse inertia that impulses act against, immovable and
+    /// kinematic objects behave as if they had infinite inertia.
+    #[must_use]
+    pub fn effective_inverse_inertia(&self) -> Matrix3<f64> {
+        if self.is_dynamic() {
+            self.inverse_inertia()
+        } else {
+            Matrix3::zeros()
+        }
+    }
+
+    /// The inverse mass that impulses act against, immovable and
+    /// kinematic objects behave as if they had infinite mass.
+    #[must_use]
+    pub fn effective_inverse_mass(&self) -> f64 {
+        if self.is_dynamic() {
+            1.0 / self.mass
+        } else {
+            0.0
+        }
+    }
+
+    #[must_use]
+    pub fn velocity(&self) -> Vector3<f64> {
+        self.kinematic
+            .as_ref()
+            .map_or_else(|| self.momentum / self.mass, |k| *k.velocity())
+    }
+
+    #[must_use]
+    pub fn angular_velocity(&self) -> Vector3<f64> {
+        self.kinematic.as_ref().map_or_else(
+            || self.inverse_inertia() * self.angular_momentum,
+            |k| *k.angular_velocity(),
+        )
+    }
+
+    /// Transforms a point from the object's space to world space.
+    #[must_use]
+    pub fn world_point(&self, local_point: &Point3<f64>) -> Point3<f64> {
+        self.position + self.rotation * local_point.coords
+    }
+
+    /// Transforms a point from world space to the object's space.
+    #[must_use]
+    pub fn local_point(&self, world_point: &Point3<f64>) -> Point3<f64> {
+        Point3::from(self.rotation.inverse() * (world_point - self.position))
     }
 
     #[must_use]
     pub fn local_velocity(&self, position: Point3<f64>) -> Vector3<f64> {
-        self.momentum / self.mass
-            + (self.inverse_inertia() * self.angular_momentum)
-                .cross(&(position - self.position))
+        self.velocity()
+            + self.angular_velocity().cross(&(position - self.position))
     }
 
     /// used for resolving collisions
@@ -101,7 +677,7 @@ impl Object {
         attack_point: Point3<f64>,
         direction: Vector3<f64>,
     ) -> f64 {
-        if self.immovable {
+        if !self.is_dynamic() {
             return 0.0;
         }
         let attack_point_vector = attack_point - self.position;
@@ -113,3 +689,79 @@ impl Object {
         )
     }
 }
+
+fn model_matrix(
+    position: &Point3<f64>,
+    rotation: &UnitQuaternion<f64>,
+    scale: Vector3<f32>,
+) -> Matrix4<f32> {
+    (Translation3::from(position.cast::<f32>()) * rotation.cast::<f32>())
+        .to_homogeneous()
+        * Scale3::from(scale).to_homogeneous()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn damping_does_not_depend_on_the_step_size() {
+        let damped = |steps: u32| {
+            let mut object = ObjectBuilder::new(Collider::Sphere(1.0))
+                .velocity(Vector3::new(1.0, 0.0, 0.0))
+                .damping(Damping {
+                    linear: 0.5,
+                    angular: 0.0,
+                })
+                .build();
+            for _ in 0..steps {
+                object.update(1.0 / f64::from(steps), Damping::NONE);
+            }
+            object.velocity().x
+        };
+        assert!((damped(10) - damped(1000)).abs() < 1e-9);
+        assert!((damped(10) - (-0.5_f64).exp()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn immovable_objects_are_not_damped() {
+        let mut object = ObjectBuilder::new(Collider::Box(1.0, 1.0, 1.0))
+            .velocity(Vector3::new(1.0, 0.0, 0.0))
+            .angular_velocity(Vector3::new(0.0, 0.5, 0.0))
+            .immovable()
+            .build();
+        let damping = Damping {
+            linear: 0.5,
+            angular: 0.5,
+        };
+        for _ in 0..60 {
+            object.update(1.0 / 60.0, damping);
+        }
+        for _ in 0..60 {
+            object.update_rk4(|_| Vector3::zeros(), 1.0 / 60.0, damping);
+        }
+        assert!((object.velocity() - Vector3::x()).magnitude() < 1e-9);
+        assert!((object.position().x - 2.0).abs() < 1e-9);
+        assert!((object.angular_velocity().y - 0.5).abs() < 1e-9);
+        assert!((object.rotation().angle() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn the_bounds_follow_the_pose() {
+        let mut object =
+            ObjectBuilder::new(Collider::Box(2.0, 1.0, 1.0)).build();
+        object.set_pose(
+            Point3::new(5.0, 0.0, 0.0),
+            UnitQuaternion::from_axis_angle(
+                &Vector3::z_axis(),
+                std::f64::consts::FRAC_PI_2,
+            ),
+        );
+        let expected = object
+            .collider()
+            .aabb(&Point3::new(5.0, 0.0, 0.0), &object.rotation());
+        assert!((object.aabb().start() - expected.start()).magnitude() < 1e-12);
+        assert!((object.aabb().end() - expected.end()).magnitude() < 1e-12);
+        assert!((object.aabb().end().y - 1.0).abs() < 1e-9);
+    }
+}
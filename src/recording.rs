@@ -1,6 +1,6 @@
-use nalgebra::{Point3, Rotation3};
+use nalgebra::{Point3, UnitQuaternion};
 
-use crate::object::Object;
+use crate::world::{BodyHandle, World};
 
 #[derive(Debug, Default)]
 struct RecordingData {
@@ -10,38 +10,49 @@ struct RecordingData {
 
 #[derive(Debug, Default)]
 pub struct Recording {
-    object_count: usize,
+    /// The recorded objects, a frame has data for each of them in this order.
+    handles: Vec<BodyHandle>,
     data: Vec<RecordingData>,
     frame_count: usize,
 }
 
 impl Recording {
-    pub fn save_frame_from(&mut self, objects: &[Object]) {
-        if objects.len() != self.object_count {
+    /// Saves the poses of the objects, the recording starts over
+    /// when objects were inserted or removed.
+    pub fn save_frame_from<T>(&mut self, world: &World<T>) {
+        if world.handles() != self.handles {
             self.data.clear();
             self.frame_count = 0;
-            self.object_count = objects.len();
+            self.handles = world.handles().to_vec();
         }
-        self.data.extend(objects.iter().map(|o| RecordingData {
-            position: o.position,
-            rotation: o.rotation.euler_angles(),
-        }));
+        self.data
+            .extend(world.objects().iter().map(|o| RecordingData {
+                position: o.position(),
+                rotation: o.rotation().euler_angles(),
+            }));
         self.frame_count += 1;
     }
 
-    pub fn load_frame_to(&self, frame_index: usize, objects: &mut [Object]) {
+    /// Moves the recorded objects that are still in the world.
+    pub fn load_frame_to<T>(&self, frame_index: usize, world: &mut World<T>) {
         if self.frame_count == 0 {
             return;
         }
         let frame_index = frame_index.clamp(0, self.last_frame_index());
-        let frame_start = frame_index * self.object_count;
-        for (o, d) in objects.iter_mut().zip(&self.data[frame_start..]) {
-            o.position = d.position;
-            o.rotation = Rotation3::from_euler_angles(
-                d.rotation.0,
-                d.rotation.1,
-                d.rotation.2,
+        let frame_start = frame_index * self.handles.len();
+        for (handle, d) in self.handles.iter().zip(&self.data[frame_start..]) {
+            let Some(o) = world.get_mut(*handle) else {
+                continue;
+            };
+            o.set_pose(
+                d.position,
+                UnitQuaternion::from_euler_angles(
+                    d.rotation.0,
+                    d.rotation.1,
+                    d.rotation.2,
+                ),
             );
+            o.previous_pose = None;
         }
     }
 
@@ -56,7 +67,7 @@ impl Recording {
     }
 
     pub fn clear(&mut self) {
-        self.object_count = 0;
+        self.handles.clear();
         self.data.clear();
         self.frame_count = 0;
     }
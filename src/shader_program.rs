@@ -5,7 +5,9 @@ use glow::{HasContext, NativeProgram};
 
 use anyhow::Result;
 
-use crate::{context::Context, vertex::Vertex};
+#[cfg(feature = "ui")]
+use crate::context::Context;
+use crate::vertex::Vertex;
 
 #[derive(Debug)]
 pub struct ShaderProgram<V: Vertex> {
@@ -36,12 +38,11 @@ unsafe fn load_shader(
 
 impl<V: Vertex> ShaderProgram<V> {
     pub fn new(
-        ctx: &Context,
+        gl: &glow::Context,
         vertex_file: impl AsRef<Path>,
         fragment_file: impl AsRef<Path>,
     ) -> Result<Self> {
         let program = unsafe {
-            let gl = &ctx.gl;
             let vertex = load_shader(gl, vertex_file, glow::VERTEX_SHADER)?;
             let fragment =
                 load_shader(gl, fragment_file, glow::FRAGMENT_SHADER)?;
@@ -68,13 +69,12 @@ impl<V: Vertex> ShaderProgram<V> {
     }
 
     pub fn with_geometry(
-        ctx: &Context,
+        gl: &glow::Context,
         vertex_file: impl AsRef<Path>,
         geometry_file: impl AsRef<Path>,
         fragment_file: impl AsRef<Path>,
     ) -> Result<Self> {
         let program = unsafe {
-            let gl = &ctx.gl;
             let vertex = load_shader(gl, vertex_file, glow::VERTEX_SHADER)?;
             let geometry =
                 load_shader(gl, geometry_file, glow::GEOMETRY_SHADER)?;
@@ -122,6 +122,7 @@ pub trait UseShaderProgram {
     unsafe fn use_shader_program<V: Vertex>(&self, program: &ShaderProgram<V>);
 }
 
+#[cfg(feature = "ui")]
 impl UseShaderProgram for Context {
     unsafe fn use_shader_program<V: Vertex>(&self, program: &ShaderProgram<V>) {
         unsafe {
@@ -5,62 +5,256 @@
 //   4. filter for possible collisions
 //   5. merge possible collisions from the three axis
 //   6. check possible collisions for real collisions
-//   7. resolve collisions one-by-one
+//   7. resolve collisions iteratively, accumulating the impulses
 //   8.
 
-use std::{collections::HashSet, vec::Vec};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    num::NonZeroUsize,
+    vec::Vec,
+};
 
-use nalgebra::{Point3, Vector3};
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+use rand::{rngs::StdRng, SeedableRng};
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
+use smallvec::SmallVec;
 
 use crate::{
     aabb::AABB,
+    aerodynamics::Aerodynamics,
+    ccd,
     collider::Collider,
+    contact_event::{
+        ContactEvent, ContactEventKind, ContactEventPoint, SensorEvent,
+        SensorEventKind,
+    },
+    desync::{HashStream, StepHash},
+    fluid::Fluid,
+    force_field::ForceField,
     gjk::{gjk, GJKResult},
-    object::Object,
+    integrator::Integrator,
+    joint::Joint,
+    manifold::{self, ManifoldPoint, OrientedBox, MAX_MANIFOLD_POINTS},
+    material::CombineRule,
+    object::{Damping, Object},
     rtree::RTree,
+    snapshot::{BodyState, Snapshot, SnapshotError},
+    world::{BodyHandle, World},
 };
 
+pub const EARTH_GRAVITY: Vector3<f64> = Vector3::new(0.0, -9.81, 0.0);
+
+/// Spawning a thread for less work than this is slower than doing it
+/// on the current thread.
+const MIN_ITEMS_PER_THREAD: usize = 128;
+
+/// A contact point continues the point of the previous step that was
+/// closer than this on the first object, and starts from its impulses.
+const WARM_START_DISTANCE: f64 = 0.05;
+
+/// How the overlapping objects are pushed apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionCorrection {
+    None,
+    /// Adds a bias to the velocity of the contacts, this adds energy
+    /// to the system.
+    Baumgarte,
+    /// Solves the penetration with separate pseudo velocities
+    /// that only move the objects.
+    SplitImpulse,
+}
+
 #[derive(Debug)]
 pub struct Simulation {
-    pub epsilon: f64,
-    pub mu: f64,
+    /// How the restitution of the materials is combined.
+    pub restitution_combine: CombineRule,
+    /// How the friction coefficients of the materials are combined.
+    pub friction_combine: CombineRule,
+    /// The number of passes the contact solver makes over all contacts.
+    pub solver_iterations: usize,
+    /// Contacts approaching slower than this do not bounce.
+    pub restitution_threshold: f64,
+    pub position_correction: PositionCorrection,
+    pub allow_sleeping: bool,
+    /// Objects slower than this can fall asleep.
+    pub sleep_linear_threshold: f64,
+    /// Objects rotating slower than this can fall asleep.
+    pub sleep_angular_threshold: f64,
+    /// How long an object has to be slow before it can fall asleep.
+    pub time_to_sleep: f64,
+    next_island_id: u64,
+    /// Penetration that is allowed without correction, this keeps resting
+    /// contacts from jittering.
+    pub penetration_slop: f64,
+    /// The fraction of the penetration that is corrected every step.
+    pub correction_factor: f64,
+    pub integrator: Integrator,
+    /// The damping of the objects that have none of their own.
+    pub damping: Damping,
+    pub gravity: Vector3<f64>,
+    pub force_fields: Vec<ForceField>,
+    pub fluids: Vec<Fluid>,
+    /// The air resistance and lift, `None` simulates a vacuum.
+    pub aerodynamics: Option<Aerodynamics>,
+    pub joints: Vec<Joint>,
+    /// Decides whether two objects can collide, it is only called
+    /// for the pairs whose collision layers and masks match.
+    pub collision_filter: Option<CollisionFilter>,
+    /// Called with every contact event after each step.
+    pub contact_callback: Option<ContactCallback>,
+    contact_events: Vec<ContactEvent>,
+    /// The pairs that touched in the previous step with their normals.
+    touching: HashMap<(BodyHandle, BodyHandle), Vector3<f64>>,
+    /// The impulses of the contact points at the end of the previous step.
+    contact_impulses: HashMap<(BodyHandle, BodyHandle), CachedImpulses>,
+    sensor_events: Vec<SensorEvent>,
+    sensor_overlaps: BTreeSet<(BodyHandle, BodyHandle)>,
+    /// The number of threads the broadphase and the narrowphase use,
+    /// one means that everything runs on the current thread.
+    pub threads: usize,
+    /// The worker threads, kept between the steps and rebuilt
+    /// when the number of threads changes.
+    thread_pool: Option<ThreadPool>,
     pub rtree: RTree<usize>,
+    /// Records the hash of the body states after every step,
+    /// see [`crate::desync`].
+    pub hash_states: bool,
+    pub state_hashes: HashStream,
+    seed: u64,
+    rng: StdRng,
+}
+
+type FilterFn = dyn Fn(&Object, &Object) -> bool + Send + Sync;
+
+/// A user callback that filters the colliding pairs
+/// before the narrowphase.
+pub struct CollisionFilter(Box<FilterFn>);
+
+impl CollisionFilter {
+    pub fn new(
+        filter: impl Fn(&Object, &Object) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self(Box::new(filter))
+    }
+}
+
+impl std::fmt::Debug for CollisionFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("CollisionFilter").finish_non_exhaustive()
+    }
+}
+
+/// A user callback that receives the contact events.
+pub struct ContactCallback(Box<dyn FnMut(&ContactEvent) + Send>);
+
+impl ContactCallback {
+    pub fn new(callback: impl FnMut(&ContactEvent) + Send + 'static) -> Self {
+        Self(Box::new(callback))
+    }
+}
+
+impl std::fmt::Debug for ContactCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ContactCallback").finish_non_exhaustive()
+    }
 }
 
 impl Default for Simulation {
     fn default() -> Self {
-        let mut rtree = RTree::new();
-        for i in 0..100 {
-            let x =
-                rand::random::<f64>().mul_add(10.0, f64::from(i % 2) * 20.0);
-            let y = rand::random::<f64>()
-                .mul_add(10.0, f64::from(i / 2 % 2) * 20.0);
-            let z = rand::random::<f64>()
-                .mul_add(10.0, f64::from(i / 4 % 2) * 20.0);
-            rtree.insert(
-                AABB::new(
-                    Point3::new(x, y, z),
-                    Point3::new(x + 1.0, y + 1.0, z + 1.0),
-                ),
-                0,
-            );
-        }
         Self {
-            epsilon: 1.0,
-            mu: 1.0,
-            rtree,
+            restitution_combine: CombineRule::Average,
+            friction_combine: CombineRule::Average,
+            solver_iterations: 10,
+            restitution_threshold: 1.0,
+            position_correction: PositionCorrection::SplitImpulse,
+            allow_sleeping: true,
+            sleep_linear_threshold: 0.05,
+            sleep_angular_threshold: 0.05,
+            time_to_sleep: 0.5,
+            next_island_id: 0,
+            penetration_slop: 0.01,
+            correction_factor: 0.2,
+            integrator: Integrator::SymplecticEuler,
+            damping: Damping::NONE,
+            gravity: EARTH_GRAVITY,
+            force_fields: Vec::new(),
+            fluids: Vec::new(),
+            aerodynamics: None,
+            joints: Vec::new(),
+            collision_filter: None,
+            contact_callback: None,
+            contact_events: Vec::new(),
+            touching: HashMap::new(),
+            contact_impulses: HashMap::new(),
+            sensor_events: Vec::new(),
+            sensor_overlaps: BTreeSet::new(),
+            threads: std::thread::available_parallelism()
+                .map_or(1, NonZeroUsize::get),
+            thread_pool: None,
+            rtree: RTree::new(),
+            hash_states: false,
+            state_hashes: HashStream::default(),
+            seed: 0,
+            rng: StdRng::seed_from_u64(0),
         }
     }
 }
 
 impl Simulation {
-    pub fn simulate(&mut self, objects: &mut [Object], delta: f64) {
+    pub fn simulate<T>(&mut self, world: &mut World<T>, delta: f64) {
+        self.joints.retain(|joint| joint.bodies_exist(world));
+        let objects = world.objects_mut();
+        if !self.allow_sleeping {
+            objects.iter_mut().for_each(Object::wake);
+        }
+        wake_islands(objects);
         for obj in objects.iter_mut() {
-            // obj.apply_impulse(
-            //     obj.position,
-            //     Vector3::new(0.0, -10.0 * delta * obj.mass, 0.0),
-            // );
-            obj.update(delta);
+            obj.previous_pose = Some((obj.position(), obj.rotation()));
+        }
+        for obj in objects.iter_mut().filter(|o| o.is_active()) {
+            let force = self.external_force(obj);
+            match self.integrator {
+                Integrator::SymplecticEuler
+                | Integrator::ImplicitGyroscopic => {
+                    obj.apply_impulse(obj.position(), force * delta);
+                }
+                Integrator::VelocityVerlet => {
+                    obj.apply_impulse(obj.position(), force * delta / 2.0);
+                }
+                // the force is integrated together with the position
+                Integrator::RungeKutta4 => {}
+            }
+        }
+        self.apply_fluids(objects, delta);
+        self.apply_aerodynamics(objects, delta);
+        for obj in objects.iter_mut() {
+            obj.prepare_kinematic(delta);
+        }
+        let step_times = self.ccd_step_times(objects, delta);
+        for (obj, step_time) in objects.iter_mut().zip(step_times) {
+            if obj.sleeping {
+                continue;
+            }
+            if self.integrator == Integrator::RungeKutta4 && obj.is_active() {
+                let mass = obj.mass();
+                obj.update_rk4(
+                    |position| self.external_force_at(position, mass),
+                    step_time,
+                    self.damping,
+                );
+            } else if self.integrator == Integrator::ImplicitGyroscopic
+                && obj.is_active()
+            {
+                obj.update_gyroscopic(step_time, self.damping);
+            } else {
+                obj.update(step_time, self.damping);
+            }
+        }
+        if self.integrator == Integrator::VelocityVerlet {
+            for obj in objects.iter_mut().filter(|o| o.is_active()) {
+                let force = self.external_force(obj);
+                obj.apply_impulse(obj.position(), force * delta / 2.0);
+            }
         }
         let rtree_contacts = self.check_contacts_rtree(objects);
         // let axis_contacts = self.check_contacts_1axis(objects);
@@ -69,11 +263,536 @@ impl Simulation {
         //     rtree_contacts.len(),
         //     axis_contacts.len()
         // );
-        let contacts = rtree_contacts;
-        for (i, j, contact) in &*contacts {
-            assert!(i < j);
-            let (s1, s2) = objects.split_at_mut(*j);
-            self.resolve_contact(&mut s1[*i], &mut s2[0], contact);
+        // sensors only report the overlaps, they are not solved
+        let (sensor_contacts, mut contacts): (Vec<_>, Vec<_>) = rtree_contacts
+            .into_vec()
+            .into_iter()
+            .partition(|(i, j, _)| objects[*i].sensor || objects[*j].sensor);
+        self.report_sensors(world, &sensor_contacts);
+        // objects touched by an awake object have to wake up
+        for (i, j, _) in &*contacts {
+            world.objects_mut()[*i].wake();
+            world.objects_mut()[*j].wake();
+        }
+        for joint in &self.joints {
+            if joint.is_active(world) {
+                world[joint.body_a].wake();
+                if let Some(b) = joint.body_b {
+                    world[b].wake();
+                }
+            }
+        }
+        wake_islands(world.objects_mut());
+        self.solve_constraints(world, &mut contacts, delta);
+        if self.position_correction == PositionCorrection::SplitImpulse {
+            self.solve_penetrations(world.objects_mut(), &mut contacts, delta);
+        }
+        self.report_contacts(world, &contacts);
+        if self.allow_sleeping {
+            self.update_sleeping(world, &contacts, delta);
+        }
+        if self.hash_states {
+            self.state_hashes.push(StepHash::of(world));
+        }
+    }
+
+    /// The random number generator of the scene, everything random
+    /// should use it, so the runs with the same seed are the same.
+    pub const fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    /// The seed the random number generator was last started from.
+    #[must_use]
+    pub const fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Starts the random number generator over from the seed.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Saves the state of the simulation and the bodies of the world.
+    #[must_use]
+    pub fn snapshot<T>(&self, world: &World<T>) -> Snapshot {
+        let mut touching: Vec<_> = self
+            .touching
+            .iter()
+            .map(|(&pair, &normal)| (pair, normal))
+            .collect();
+        touching.sort_unstable_by_key(|(pair, _)| *pair);
+        let mut contact_impulses: Vec<_> = self
+            .contact_impulses
+            .iter()
+            .map(|(&pair, impulses)| (pair, impulses.clone()))
+            .collect();
+        contact_impulses.sort_unstable_by_key(|(pair, _)| *pair);
+        Snapshot {
+            bodies: world
+                .iter()
+                .map(|(handle, o)| BodyState {
+                    handle,
+                    position: o.position(),
+                    rotation: o.rotation(),
+                    momentum: o.momentum,
+                    angular_momentum: o.angular_momentum,
+                    sleeping: o.sleeping,
+                    sleep_timer: o.sleep_timer,
+                    island: o.island,
+                    kinematic_time: o.kinematic.as_ref().map(|k| k.time),
+                })
+                .collect(),
+            next_island_id: self.next_island_id,
+            touching,
+            contact_impulses,
+            sensor_overlaps: self.sensor_overlaps.iter().copied().collect(),
+        }
+    }
+
+    /// Rolls the simulation and the bodies back to the snapshot,
+    /// the events of the last step are cleared.
+    pub fn restore<T>(
+        &mut self,
+        world: &mut World<T>,
+        snapshot: &Snapshot,
+    ) -> Result<(), SnapshotError> {
+        if world.len() != snapshot.bodies.len()
+            || !snapshot.bodies.iter().all(|b| world.contains(b.handle))
+        {
+            return Err(SnapshotError::BodiesChanged);
+        }
+        for body in &snapshot.bodies {
+            let o = &mut world[body.handle];
+            o.set_pose(body.position, body.rotation);
+            o.momentum = body.momentum;
+            o.angular_momentum = body.angular_momentum;
+            o.sleeping = body.sleeping;
+            o.sleep_timer = body.sleep_timer;
+            o.island = body.island;
+            if let (Some(kinematic), Some(time)) =
+                (&mut o.kinematic, body.kinematic_time)
+            {
+                kinematic.time = time;
+            }
+            o.previous_pose = None;
+        }
+        self.next_island_id = snapshot.next_island_id;
+        self.touching = snapshot.touching.iter().copied().collect();
+        self.contact_impulses =
+            snapshot.contact_impulses.iter().cloned().collect();
+        self.sensor_overlaps =
+            snapshot.sensor_overlaps.iter().copied().collect();
+        self.contact_events.clear();
+        self.sensor_events.clear();
+        Ok(())
+    }
+
+    /// The contact events of the last step.
+    #[must_use]
+    pub fn contact_events(&self) -> &[ContactEvent] {
+        &self.contact_events
+    }
+
+    /// The sensor events of the last step.
+    #[must_use]
+    pub fn sensor_events(&self) -> &[SensorEvent] {
+        &self.sensor_events
+    }
+
+    /// The `(sensor, object)` pairs that currently overlap.
+    #[must_use]
+    pub const fn sensor_overlaps(&self) -> &BTreeSet<(BodyHandle, BodyHandle)> {
+        &self.sensor_overlaps
+    }
+
+    /// Compares the sensor overlaps with the ones from the previous step,
+    /// and reports the differences as sensor events.
+    fn report_sensors<T>(
+        &mut self,
+        world: &World<T>,
+        sensor_contacts: &[(usize, usize, Contact)],
+    ) {
+        self.sensor_events.clear();
+        let mut overlaps = BTreeSet::new();
+        for &(i, j, _) in sensor_contacts {
+            let (i, j) = if world.objects()[i].sensor {
+                (i, j)
+            } else {
+                (j, i)
+            };
+            let (sensor, object) = (world.handles()[i], world.handles()[j]);
+            // compound bodies can overlap with several parts
+            if !overlaps.insert((sensor, object)) {
+                continue;
+            }
+            if !self.sensor_overlaps.remove(&(sensor, object)) {
+                self.sensor_events.push(SensorEvent {
+                    kind: SensorEventKind::Enter,
+                    sensor,
+                    object,
+                });
+            }
+        }
+        for (sensor, object) in std::mem::take(&mut self.sensor_overlaps) {
+            // the overlaps of sleeping objects are not checked,
+            // removed objects stop overlapping
+            let unchecked = match (world.get(sensor), world.get(object)) {
+                (Some(s), Some(o)) => !s.is_moving() && !o.is_moving(),
+                _ => false,
+            };
+            if unchecked {
+                overlaps.insert((sensor, object));
+            } else {
+                self.sensor_events.push(SensorEvent {
+                    kind: SensorEventKind::Exit,
+                    sensor,
+                    object,
+                });
+            }
+        }
+        self.sensor_overlaps = overlaps;
+    }
+
+    /// Compares the contacts with the ones from the previous step,
+    /// and reports the differences as contact events.
+    fn report_contacts<T>(
+        &mut self,
+        world: &World<T>,
+        contacts: &[(usize, usize, Contact)],
+    ) {
+        self.contact_events.clear();
+        let mut touching = HashMap::with_capacity(contacts.len());
+        let mut event_indices: HashMap<_, usize> =
+            HashMap::with_capacity(contacts.len());
+        for (i, j, contact) in contacts {
+            let event = contact_event(
+                (world.handles()[*i], world.handles()[*j]),
+                contact,
+            );
+            // compound bodies can touch with several parts,
+            // their points are merged into the event of the body pair
+            if let Some(&index) = event_indices.get(&event.bodies) {
+                self.contact_events[index].points.extend(event.points);
+                continue;
+            }
+            let kind = if self.touching.remove(&event.bodies).is_some() {
+                ContactEventKind::Persist
+            } else {
+                ContactEventKind::Begin
+            };
+            touching.insert(event.bodies, event.normal);
+            event_indices.insert(event.bodies, self.contact_events.len());
+            self.contact_events.push(ContactEvent { kind, ..event });
+        }
+        let mut ended: Vec<_> = self.touching.drain().collect();
+        ended.sort_unstable_by_key(|(bodies, _)| *bodies);
+        for ((a, b), normal) in ended {
+            // the contacts of sleeping objects are not checked,
+            // but they are still touching, removed objects stop touching
+            let unchecked = match (world.get(a), world.get(b)) {
+                (Some(o1), Some(o2)) => !o1.is_moving() && !o2.is_moving(),
+                _ => false,
+            };
+            if unchecked {
+                touching.insert((a, b), normal);
+            } else {
+                self.contact_events.push(ContactEvent {
+                    kind: ContactEventKind::End,
+                    bodies: (a, b),
+                    normal,
+                    points: SmallVec::new(),
+                });
+            }
+        }
+        self.touching = touching;
+        if let Some(callback) = &mut self.contact_callback {
+            for event in &self.contact_events {
+                (callback.0)(event);
+            }
+        }
+    }
+
+    /// Objects that moved slowly for long enough get to sleep together
+    /// with every object they are in contact with.
+    fn update_sleeping<T>(
+        &mut self,
+        world: &mut World<T>,
+        contacts: &[(usize, usize, Contact)],
+        delta: f64,
+    ) {
+        let joint_connections: Vec<_> = self
+            .joints
+            .iter()
+            .filter_map(|joint| {
+                Some((
+                    world.index_of(joint.body_a)?,
+                    world.index_of(joint.body_b?)?,
+                ))
+            })
+            .collect();
+        let objects = world.objects_mut();
+        for obj in objects.iter_mut().filter(|o| o.is_active()) {
+            let linear_velocity = obj.momentum.magnitude() / obj.mass();
+            let angular_velocity =
+                (obj.inverse_inertia() * obj.angular_momentum).magnitude();
+            if linear_velocity < self.sleep_linear_threshold
+                && angular_velocity < self.sleep_angular_threshold
+            {
+                obj.sleep_timer += delta;
+            } else {
+                obj.sleep_timer = 0.0;
+            }
+        }
+
+        // immovable objects do not connect islands
+        let mut islands = UnionFind::new(objects.len());
+        let connections = contacts
+            .iter()
+            .map(|(i, j, _)| (*i, *j))
+            .chain(joint_connections);
+        for (i, j) in connections {
+            if objects[i].is_active() && objects[j].is_active() {
+                islands.union(i, j);
+            }
+        }
+        let mut can_sleep = vec![true; objects.len()];
+        for (i, obj) in objects.iter().enumerate() {
+            if obj.is_active() && obj.sleep_timer < self.time_to_sleep {
+                can_sleep[islands.find(i)] = false;
+            }
+        }
+        let mut island_ids = HashMap::new();
+        for (i, obj) in objects.iter_mut().enumerate() {
+            let root = islands.find(i);
+            if obj.is_active() && can_sleep[root] {
+                let id = *island_ids.entry(root).or_insert_with(|| {
+                    self.next_island_id += 1;
+                    self.next_island_id
+                });
+                obj.sleep(id);
+            }
+        }
+    }
+
+    /// Sequential impulse solver: every iteration goes over all contacts
+    /// and joints, and applies the difference between the new and the
+    /// previously accumulated (clamped) impulse.
+    ///
+    /// The contacts start from the impulses of the previous step, so the
+    /// impulses holding up a stack do not have to build up again in every
+    /// step.
+    ///
+    /// Joints are always corrected with a Baumgarte bias.
+    fn solve_constraints<T>(
+        &mut self,
+        world: &mut World<T>,
+        contacts: &mut [(usize, usize, Contact)],
+        delta: f64,
+    ) {
+        for (i, j, contact) in &mut *contacts {
+            let (o1, o2) = pair_mut(world.objects_mut(), *i, *j);
+            self.prepare_contact(o1, o2, contact, delta);
+        }
+        // the target velocities are calculated before any impulse
+        let pairs: Vec<_> = contacts
+            .iter()
+            .map(|(i, j, _)| (world.handles()[*i], world.handles()[*j]))
+            .collect();
+        for ((i, j, contact), pair) in contacts.iter_mut().zip(&pairs) {
+            if let Some(cached) = self.contact_impulses.get(pair) {
+                let (o1, o2) = pair_mut(world.objects_mut(), *i, *j);
+                warm_start(o1, o2, contact, cached);
+            }
+        }
+        let mut joints = std::mem::take(&mut self.joints);
+        let mut active_joints: Vec<_> = joints
+            .iter_mut()
+            .filter(|joint| joint.is_active(world))
+            .collect();
+        for joint in &mut active_joints {
+            joint.prepare();
+        }
+        let bias_factor = self.correction_factor / delta;
+        for _ in 0..self.solver_iterations {
+            for (i, j, contact) in &mut *contacts {
+                let (o1, o2) = pair_mut(world.objects_mut(), *i, *j);
+                Self::solve_contact(o1, o2, contact);
+            }
+            for joint in &mut active_joints {
+                joint.solve(world, bias_factor);
+            }
+        }
+        self.joints = joints;
+        self.contact_impulses.clear();
+        for ((i, _, contact), pair) in contacts.iter().zip(pairs) {
+            let o1 = &world.objects()[*i];
+            self.contact_impulses.entry(pair).or_default().extend(
+                contact.points.iter().map(|point| CachedImpulse {
+                    point: o1.local_point(&point.points.0),
+                    normal_impulse: point.normal_impulse,
+                    friction_impulse: point.friction_impulse,
+                }),
+            );
+        }
+    }
+
+    /// Continuous collision detection: the objects that opted in are only
+    /// moved until their first time of impact, so they cannot tunnel through
+    /// other objects. The candidates are found with swept AABBs.
+    ///
+    /// The objects are moved a bit past the time of impact, so their contact
+    /// is found in this step instead of getting stuck right before it.
+    ///
+    /// The rest of the step after the time of impact is not integrated,
+    /// so a fast object loses that part of its motion: it stops at the
+    /// impact in this step and only moves with the velocity from the
+    /// contact solver in the next one.
+    fn ccd_step_times(&mut self, objects: &[Object], delta: f64) -> Vec<f64> {
+        let mut step_times = vec![delta; objects.len()];
+        if !objects.iter().any(|o| o.ccd && o.is_active()) {
+            return step_times;
+        }
+        self.rtree.clear();
+        for (i, obj) in objects.iter().enumerate() {
+            self.rtree.insert(obj.swept_aabb(delta), i);
+        }
+        for (i, obj) in objects.iter().enumerate() {
+            if !obj.ccd || !obj.is_active() {
+                continue;
+            }
+            let motion = obj.motion();
+            let time_of_impact = self
+                .rtree
+                .search(&obj.swept_aabb(delta))
+                .into_iter()
+                .filter(|&&j| {
+                    // objects fly through sensors
+                    j != i
+                        && !objects[j].sensor
+                        && self.should_collide(obj, &objects[j])
+                })
+                .filter_map(|&j| {
+                    ccd::time_of_impact(
+                        (obj.collider(), &motion),
+                        (objects[j].collider(), &objects[j].motion()),
+                        delta,
+                    )
+                })
+                // touching objects are handled by the contact solver
+                .filter(|&time_of_impact| time_of_impact > 0.0)
+                .min_by(f64::total_cmp);
+            if let Some(time_of_impact) = time_of_impact {
+                let speed = motion.velocity.magnitude();
+                step_times[i] =
+                    (time_of_impact + self.penetration_slop / speed).min(delta);
+            }
+        }
+        step_times
+    }
+
+    /// Whether the pair can collide according to the collision layers
+    /// and the collision filter.
+    fn should_collide(&self, o1: &Object, o2: &Object) -> bool {
+        !(o1.sensor && o2.sensor)
+            && o1.can_collide(o2)
+            && self
+                .collision_filter
+                .as_ref()
+                .is_none_or(|filter| (filter.0)(o1, o2))
+    }
+
+    /// The sum of gravity and the force fields acting on the object.
+    #[must_use]
+    pub fn external_force(&self, obj: &Object) -> Vector3<f64> {
+        self.external_force_at(&obj.position(), obj.mass())
+    }
+
+    /// The sum of gravity and the force fields acting on a mass
+    /// at the position.
+    #[must_use]
+    pub fn external_force_at(
+        &self,
+        position: &Point3<f64>,
+        mass: f64,
+    ) -> Vector3<f64> {
+        self.force_fields
+            .iter()
+            .map(|field| field.force_at(position, mass))
+            .fold(self.gravity * mass, |sum, force| sum + force)
+    }
+
+    /// Applies the buoyant force at the center of the submerged volume,
+    /// and the drag proportional to the submerged fraction of the object.
+    ///
+    /// The fluids are applied as impulses before moving the objects
+    /// with every integrator.
+    fn apply_fluids(&self, objects: &mut [Object], delta: f64) {
+        if self.fluids.is_empty() {
+            return;
+        }
+        for obj in objects.iter_mut().filter(|o| o.is_active()) {
+            for fluid in &self.fluids {
+                let Some(submersion) = fluid.submersion(
+                    obj.collider(),
+                    &obj.position(),
+                    &obj.rotation(),
+                    obj.aabb(),
+                ) else {
+                    continue;
+                };
+                let fraction =
+                    (submersion.volume / obj.collider().volume()).min(1.0);
+                let buoyancy =
+                    -self.gravity * (fluid.density * submersion.volume);
+                obj.apply_impulse(submersion.center, buoyancy * delta);
+                let linear_drag =
+                    1.0 - (-fluid.linear_drag * fraction * delta).exp();
+                let angular_drag =
+                    1.0 - (-fluid.angular_drag * fraction * delta).exp();
+                obj.apply_impulse(obj.position(), -obj.momentum * linear_drag);
+                obj.apply_angular_impulse(-obj.angular_momentum * angular_drag);
+            }
+        }
+    }
+
+    /// Applies the air drag and the Magnus lift at the center of mass,
+    /// the drag of a single step cannot reverse the motion.
+    fn apply_aerodynamics(&self, objects: &mut [Object], delta: f64) {
+        let Some(aerodynamics) = &self.aerodynamics else {
+            return;
+        };
+        for obj in objects.iter_mut().filter(|o| o.is_active()) {
+            let velocity = obj.velocity();
+            let mut drag =
+                aerodynamics.drag(obj.collider(), &obj.rotation(), &velocity)
+                    * delta;
+            if drag.magnitude_squared() > obj.momentum.magnitude_squared() {
+                drag = -obj.momentum;
+            }
+            let lift = aerodynamics.lift(
+                obj.collider(),
+                &velocity,
+                &obj.angular_velocity(),
+            ) * delta;
+            obj.apply_impulse(obj.position(), drag + lift);
+        }
+    }
+
+    /// Starts the worker threads if the number of threads changed.
+    fn update_thread_pool(&mut self) {
+        if self.threads <= 1 {
+            self.thread_pool = None;
+        } else if self
+            .thread_pool
+            .as_ref()
+            .is_none_or(|pool| pool.current_num_threads() != self.threads)
+        {
+            self.thread_pool = ThreadPoolBuilder::new()
+                .num_threads(self.threads)
+                .build()
+                .ok();
         }
     }
 
@@ -85,21 +804,42 @@ impl Simulation {
         for (i, obj) in objects.iter().enumerate() {
             self.rtree.insert(obj.aabb().clone(), i);
         }
-        objects
+        // objects cannot be shared between threads,
+        // the searches and the narrowphase use copies of their data
+        let moving: Vec<_> = objects.iter().map(Object::is_moving).collect();
+        let queries: Vec<_> = objects
             .iter()
             .enumerate()
-            .flat_map(|(i, obj)| {
-                self.rtree
-                    .search(obj.aabb())
-                    .into_iter()
-                    .map(move |j| (i, *j))
-            })
-            .filter(|(i, j)| i < j)
-            .filter_map(|(i, j)| {
-                self.check_contact_gjk(&objects[i], &objects[j])
-                    .map(|contact| (i, j, contact))
-            })
-            .collect()
+            .filter(|(_, obj)| obj.is_moving())
+            .map(|(i, obj)| (i, obj.aabb().clone()))
+            .collect();
+        self.update_thread_pool();
+        let thread_pool = self.thread_pool.as_ref();
+        let rtree = &self.rtree;
+        let pairs: Vec<_> = parallel_map(&queries, thread_pool, |(i, aabb)| {
+            rtree
+                .search(aabb)
+                .into_iter()
+                .map(|&j| (*i, j))
+                // pairs of moving objects are found from both sides
+                .filter(|&(i, j)| i < j || (i > j && !moving[j]))
+                .map(|(i, j)| (i.min(j), i.max(j)))
+                .collect::<Vec<_>>()
+        })
+        .into_iter()
+        .flatten()
+        .filter(|&(i, j)| self.should_collide(&objects[i], &objects[j]))
+        .collect();
+        let bodies: Vec<_> = objects.iter().map(Body::from).collect();
+        parallel_map(&pairs, thread_pool, |&(i, j)| {
+            Self::check_contacts_parts(&bodies[i], &bodies[j])
+                .into_iter()
+                .map(|contact| (i, j, contact))
+                .collect::<Vec<_>>()
+        })
+        .into_iter()
+        .flatten()
+        .collect()
     }
 
     #[allow(dead_code)]
@@ -134,7 +874,9 @@ impl Simulation {
             match interval {
                 Interval::Start => {
                     for &j in &open_intervals {
-                        if objects[i].aabb().overlaps_yz(objects[j].aabb()) {
+                        if (objects[i].is_moving() || objects[j].is_moving())
+                            && objects[i].aabb().overlaps_yz(objects[j].aabb())
+                        {
                             potential_contacts.push((i.min(j), i.max(j)));
                         }
                     }
@@ -148,52 +890,60 @@ impl Simulation {
 
         potential_contacts
             .iter()
+            .filter(|&&(i, j)| self.should_collide(&objects[i], &objects[j]))
             .filter_map(|&(i, j)| {
-                self.check_contact(&objects[i], &objects[j])
-                    .map(|contact| (i, j, contact))
+                Self::check_contact(
+                    &Body::from(&objects[i]),
+                    &Body::from(&objects[j]),
+                )
+                .map(|contact| (i, j, contact))
             })
             .collect()
     }
 
-    fn resolve_contact(
-        &self,
-        o1: &mut Object,
-        o2: &mut Object,
-        contact: &Contact,
-    ) {
-        let relative_velocity = o1.local_velocity(contact.points.0)
-            - o2.local_velocity(contact.points.1);
-        let normal_velocity = relative_velocity.dot(&contact.normal);
-        #[allow(clippy::if_same_then_else)]
-        if normal_velocity < -f64::EPSILON {
-            self.resolve_colliding_contact(o1, o2, contact);
-        } else if normal_velocity < f64::EPSILON {
-            // TODO: handle resting contact separately
-            self.resolve_colliding_contact(o1, o2, contact);
-        } else {
-            // separating contact
-        }
-    }
-
-    #[allow(clippy::unused_self)]
-    fn check_contact(&self, o1: &Object, o2: &Object) -> Option<Contact> {
-        match (o1.collider, o2.collider) {
-            (Collider::Sphere(r1), Collider::Sphere(r2)) => {
+    /// Checks the contacts between every pair of parts of the bodies whose
+    /// bounds overlap, compound bodies can touch in several places.
+    fn check_contacts_parts(o1: &Body, o2: &Body) -> SmallVec<[Contact; 1]> {
+        let (parts1, parts2) = (o1.parts(), o2.parts());
+        if parts1.len() == 1 && parts2.len() == 1 {
+            // the bounds of the bodies already overlap
+            return Self::check_contact_gjk(&parts1[0], &parts2[0])
+                .into_iter()
+                .collect();
+        }
+        let bounds2: SmallVec<[AABB; 1]> =
+            parts2.iter().map(Body::aabb).collect();
+        let mut contacts = SmallVec::new();
+        for part1 in &parts1 {
+            let bounds1 = part1.aabb();
+            for (part2, bounds2) in parts2.iter().zip(&bounds2) {
+                if bounds1.overlaps(bounds2) {
+                    contacts.extend(Self::check_contact_gjk(part1, part2));
+                }
+            }
+        }
+        contacts
+    }
+
+    fn check_contact(o1: &Body, o2: &Body) -> Option<Contact> {
+        match (&o1.collider, &o2.collider) {
+            (&Collider::Sphere(r1), &Collider::Sphere(r2)) => {
                 let center_distance = o1.position - o2.position;
                 if center_distance.magnitude() <= r1 + r2 {
                     let contact_normal = center_distance.normalize();
-                    Some(Contact {
-                        points: (
+                    Some(Contact::new(
+                        (
                             o1.position - contact_normal * r1,
                             o2.position + contact_normal * r2,
                         ),
-                        normal: contact_normal,
-                    })
+                        contact_normal,
+                        r1 + r2 - center_distance.magnitude(),
+                    ))
                 } else {
                     None
                 }
             }
-            (Collider::Sphere(r), Collider::Box(w, h, d)) => {
+            (&Collider::Sphere(r), &Collider::Box(w, h, d)) => {
                 let half_size = Vector3::new(w, h, d) / 2.0;
                 let box_space_position =
                     o2.rotation.inverse() * (o1.position - o2.position);
@@ -211,79 +961,1126 @@ impl Simulation {
                 let world_space_normal = o2.rotation * box_space_normal;
                 let world_space_closest_offset =
                     o2.rotation * box_space_closest_offset;
-                Some(Contact {
-                    points: (
+                Some(Contact::new(
+                    (
                         o1.position - world_space_normal,
                         o2.position + world_space_closest_offset,
                     ),
-                    normal: world_space_normal.normalize(),
-                })
+                    world_space_normal.normalize(),
+                    r - box_space_normal.magnitude(),
+                ))
             }
             _ => None,
         }
     }
 
-    #[allow(clippy::unused_self)]
-    fn check_contact_gjk(&self, o1: &Object, o2: &Object) -> Option<Contact> {
+    fn check_contact_gjk(o1: &Body, o2: &Body) -> Option<Contact> {
         match gjk(
-            &(o1.position, o1.rotation, o1.collider),
-            &(o2.position, o2.rotation, o2.collider),
+            &(o1.position, o1.rotation, o1.collider.clone()),
+            &(o2.position, o2.rotation, o2.collider.clone()),
         ) {
-            GJKResult::Contact { points, normal } => Some(Contact {
-                points: (points.0.into(), points.1.into()),
+            GJKResult::Contact {
+                points,
                 normal,
-            }),
+                depth,
+            } => {
+                if let (
+                    &Collider::Box(w1, h1, d1),
+                    &Collider::Box(w2, h2, d2),
+                ) = (&o1.collider, &o2.collider)
+                {
+                    let manifold = manifold::box_box(
+                        &OrientedBox::new(
+                            o1.position,
+                            o1.rotation,
+                            Vector3::new(w1, h1, d1),
+                        ),
+                        &OrientedBox::new(
+                            o2.position,
+                            o2.rotation,
+                            Vector3::new(w2, h2, d2),
+                        ),
+                        &normal,
+                    );
+                    if !manifold.is_empty() {
+                        return Some(Contact::from_manifold(normal, &manifold));
+                    }
+                }
+                Some(Contact::new(
+                    (points.0.into(), points.1.into()),
+                    normal,
+                    depth,
+                ))
+            }
             GJKResult::NoContact => None,
             GJKResult::UnknownContact(_) => {
                 eprintln!(
                     "gjk gave unknown contact, falling back to other solution"
                 );
-                self.check_contact(o1, o2)
+                Self::check_contact(o1, o2)
             }
         }
     }
 
-    fn resolve_colliding_contact(
+    /// Calculates the velocity the contact should separate with after the
+    /// collision. Contacts slower than the restitution threshold are resting
+    /// contacts, they should not bounce.
+    /// With Baumgarte stabilization the target velocity is increased
+    /// to push the objects apart.
+    fn prepare_contact(
         &self,
+        o1: &Object,
+        o2: &Object,
+        contact: &mut Contact,
+        delta: f64,
+    ) {
+        contact.material = ContactMaterial {
+            restitution: self
+                .restitution_combine
+                .combine(o1.material().restitution, o2.material().restitution),
+            static_friction: self.friction_combine.combine(
+                o1.material().static_friction,
+                o2.material().static_friction,
+            ),
+            dynamic_friction: self.friction_combine.combine(
+                o1.material().dynamic_friction,
+                o2.material().dynamic_friction,
+            ),
+            rolling_friction: self.friction_combine.combine(
+                o1.material().rolling_friction,
+                o2.material().rolling_friction,
+            ),
+        };
+        for point in &mut contact.points {
+            let relative_velocity = o1.local_velocity(point.points.0)
+                - o2.local_velocity(point.points.1);
+            let normal_velocity = relative_velocity.dot(&contact.normal);
+            point.target_normal_velocity =
+                if normal_velocity < -self.restitution_threshold {
+                    -contact.material.restitution * normal_velocity
+                } else {
+                    0.0
+                };
+            if self.position_correction == PositionCorrection::Baumgarte {
+                point.target_normal_velocity = point
+                    .target_normal_velocity
+                    .max(self.penetration_bias(point.depth, delta));
+            }
+        }
+    }
+
+    fn penetration_bias(&self, depth: f64, delta: f64) -> f64 {
+        self.correction_factor * (depth - self.penetration_slop).max(0.0)
+            / delta
+    }
+
+    /// Split impulse position correction: the penetrations are solved with
+    /// pseudo velocities, which are integrated into the positions and then
+    /// thrown away, so the correction does not add energy.
+    fn solve_penetrations(
+        &self,
+        objects: &mut [Object],
+        contacts: &mut [(usize, usize, Contact)],
+        delta: f64,
+    ) {
+        let mut pseudo_velocities =
+            vec![PseudoVelocity::default(); objects.len()];
+        for _ in 0..self.solver_iterations {
+            for (i, j, contact) in &mut *contacts {
+                let (o1, o2) = (&objects[*i], &objects[*j]);
+                let (v1, v2) = pair_mut(&mut pseudo_velocities, *i, *j);
+                for point in &mut contact.points {
+                    let effectiveness = o1
+                        .impulse_effectiveness(point.points.0, contact.normal)
+                        + o2.impulse_effectiveness(
+                            point.points.1,
+                            contact.normal,
+                        );
+                    if effectiveness <= f64::EPSILON {
+                        continue;
+                    }
+                    let normal_velocity = (v1.at(o1, point.points.0)
+                        - v2.at(o2, point.points.1))
+                    .dot(&contact.normal);
+                    let impulse_strength = (self
+                        .penetration_bias(point.depth, delta)
+                        - normal_velocity)
+                        / effectiveness;
+                    let accumulated_impulse =
+                        (point.pseudo_impulse + impulse_strength).max(0.0);
+                    let impulse = contact.normal
+                        * (accumulated_impulse - point.pseudo_impulse);
+                    point.pseudo_impulse = accumulated_impulse;
+                    v1.apply_impulse(o1, point.points.0, impulse);
+                    v2.apply_impulse(o2, point.points.1, -impulse);
+                }
+            }
+        }
+        for (i, j, _) in &*contacts {
+            for k in [*i, *j] {
+                let velocity = std::mem::take(&mut pseudo_velocities[k]);
+                objects[k].displace(
+                    velocity.linear * delta,
+                    velocity.angular * delta,
+                );
+            }
+        }
+    }
+
+    fn solve_contact(o1: &mut Object, o2: &mut Object, contact: &mut Contact) {
+        for point in &mut contact.points {
+            Self::solve_contact_point(
+                o1,
+                o2,
+                &contact.normal,
+                &contact.material,
+                point,
+            );
+        }
+        Self::solve_rolling_friction(o1, o2, contact);
+    }
+
+    fn solve_contact_point(
+        o1: &mut Object,
+        o2: &mut Object,
+        normal: &Vector3<f64>,
+        material: &ContactMaterial,
+        point: &mut ContactPoint,
+    ) {
+        let normal_effectiveness = o1
+            .impulse_effectiveness(point.points.0, *normal)
+            + o2.impulse_effectiveness(point.points.1, *normal);
+        if normal_effectiveness <= f64::EPSILON {
+            return;
+        }
+        let relative_velocity = o1.local_velocity(point.points.0)
+            - o2.local_velocity(point.points.1);
+        let normal_impulse_strength = (point.target_normal_velocity
+            - normal.dot(&relative_velocity))
+            / normal_effectiveness;
+        let accumulated_normal_impulse =
+            (point.normal_impulse + normal_impulse_strength).max(0.0);
+        let normal_impulse =
+            normal * (accumulated_normal_impulse - point.normal_impulse);
+        point.normal_impulse = accumulated_normal_impulse;
+        o1.apply_impulse(point.points.0, normal_impulse);
+        o2.apply_impulse(point.points.1, -normal_impulse);
+
+        let relative_velocity = o1.local_velocity(point.points.0)
+            - o2.local_velocity(point.points.1);
+        let nonnormal_relative_velocity =
+            relative_velocity - normal * normal.dot(&relative_velocity);
+        let Some(friction_direction) =
+            (-nonnormal_relative_velocity).try_normalize(f64::EPSILON)
+        else {
+            return;
+        };
+        let friction_effectiveness = o1
+            .impulse_effectiveness(point.points.0, friction_direction)
+            + o2.impulse_effectiveness(point.points.1, friction_direction);
+        if friction_effectiveness <= f64::EPSILON {
+            return;
+        }
+        let friction_impulse_strength =
+            nonnormal_relative_velocity.magnitude() / friction_effectiveness;
+        // the accumulated friction impulse is clamped to the friction cone,
+        // the contact slides if static friction cannot hold it
+        let mut accumulated_friction_impulse = point.friction_impulse
+            + friction_direction * friction_impulse_strength;
+        if accumulated_friction_impulse.magnitude()
+            > material.static_friction * point.normal_impulse
+        {
+            accumulated_friction_impulse = accumulated_friction_impulse
+                .cap_magnitude(
+                    material.dynamic_friction * point.normal_impulse,
+                );
+        }
+        let friction_impulse =
+            accumulated_friction_impulse - point.friction_impulse;
+        point.friction_impulse = accumulated_friction_impulse;
+        o1.apply_impulse(point.points.0, friction_impulse);
+        o2.apply_impulse(point.points.1, -friction_impulse);
+    }
+
+    /// Slows down the relative rolling of the objects, the spinning around
+    /// the contact normal is not affected.
+    fn solve_rolling_friction(
         o1: &mut Object,
         o2: &mut Object,
-        contact: &Contact,
+        contact: &mut Contact,
     ) {
-        let relative_velocity = o1.local_velocity(contact.points.0)
-            - o2.local_velocity(contact.points.1);
-        let normal_impulse_strength = -(self.epsilon + 1.0)
-            * contact.normal.dot(&relative_velocity)
-            / (o1.impulse_effectiveness(contact.points.0, contact.normal)
-                + o2.impulse_effectiveness(contact.points.1, contact.normal));
-
-        let nonnormal_relative_velocity = relative_velocity
-            - contact.normal * contact.normal.dot(&relative_velocity);
-        let nonnormal_relative_velocity_direction =
-            -nonnormal_relative_velocity
-                .try_normalize(f64::EPSILON)
-                .unwrap_or_else(Vector3::x);
-
-        let friction_impulse_max_strength =
-            -nonnormal_relative_velocity_direction.dot(&relative_velocity)
-                / (o1.impulse_effectiveness(
-                    contact.points.0,
-                    nonnormal_relative_velocity_direction,
-                ) + o2.impulse_effectiveness(
-                    contact.points.1,
-                    nonnormal_relative_velocity_direction,
-                ));
-        let friction_impulse_strength = friction_impulse_max_strength
-            .min(self.mu * normal_impulse_strength);
-
-        let impulse = contact.normal * normal_impulse_strength
-            + nonnormal_relative_velocity_direction * friction_impulse_strength;
-        o1.apply_impulse(contact.points.0, impulse);
-        o2.apply_impulse(contact.points.1, -impulse);
+        let normal_impulse: f64 =
+            contact.points.iter().map(|p| p.normal_impulse).sum();
+        let max_impulse = contact.material.rolling_friction * normal_impulse;
+        if max_impulse <= 0.0 {
+            return;
+        }
+        let relative_angular_velocity =
+            o1.angular_velocity() - o2.angular_velocity();
+        let rolling_velocity = relative_angular_velocity
+            - contact.normal * contact.normal.dot(&relative_angular_velocity);
+        let Some(direction) = (-rolling_velocity).try_normalize(f64::EPSILON)
+        else {
+            return;
+        };
+        let effectiveness = direction.dot(
+            &((o1.effective_inverse_inertia()
+                + o2.effective_inverse_inertia())
+                * direction),
+        );
+        if effectiveness <= f64::EPSILON {
+            return;
+        }
+        let accumulated_impulse = (contact.rolling_impulse
+            + direction * rolling_velocity.magnitude() / effectiveness)
+            .cap_magnitude(max_impulse);
+        let impulse = accumulated_impulse - contact.rolling_impulse;
+        contact.rolling_impulse = accumulated_impulse;
+        o1.apply_angular_impulse(impulse);
+        o2.apply_angular_impulse(-impulse);
     }
 }
 
+/// The event of a contact, the body with the smaller handle comes first,
+/// the normal and the points are flipped to match.
+fn contact_event(
+    (a, b): (BodyHandle, BodyHandle),
+    contact: &Contact,
+) -> ContactEvent {
+    let flip = a > b;
+    let sign = if flip { -1.0 } else { 1.0 };
+    ContactEvent {
+        kind: ContactEventKind::Begin,
+        bodies: if flip { (b, a) } else { (a, b) },
+        normal: contact.normal * sign,
+        points: contact
+            .points
+            .iter()
+            .map(|point| ContactEventPoint {
+                points: if flip {
+                    (point.points.1, point.points.0)
+                } else {
+                    point.points
+                },
+                depth: point.depth,
+                normal_impulse: point.normal_impulse,
+                friction_impulse: point.friction_impulse * sign,
+            })
+            .collect(),
+    }
+}
+
+/// Applies the impulses the contact points ended the previous step with,
+/// the solver continues from them instead of starting from zero.
+fn warm_start(
+    o1: &mut Object,
+    o2: &mut Object,
+    contact: &mut Contact,
+    cached: &[CachedImpulse],
+) {
+    for point in &mut contact.points {
+        let local_point = o1.local_point(&point.points.0);
+        let Some(previous) = cached
+            .iter()
+            .map(|c| (c, (c.point - local_point).magnitude()))
+            .filter(|(_, distance)| *distance < WARM_START_DISTANCE)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(c, _)| c)
+        else {
+            continue;
+        };
+        // the normal can turn a bit between the steps
+        let friction_impulse = previous.friction_impulse
+            - contact.normal * contact.normal.dot(&previous.friction_impulse);
+        point.normal_impulse = previous.normal_impulse;
+        point.friction_impulse = friction_impulse;
+        let impulse =
+            contact.normal * previous.normal_impulse + friction_impulse;
+        o1.apply_impulse(point.points.0, impulse);
+        o2.apply_impulse(point.points.1, -impulse);
+    }
+}
+
+/// Wakes every object that fell asleep in the same island as an object
+/// which has been woken up since.
+fn wake_islands(objects: &mut [Object]) {
+    let woken_islands: HashSet<_> = objects
+        .iter_mut()
+        .filter(|o| !o.sleeping)
+        .filter_map(|o| o.island.take())
+        .collect();
+    if woken_islands.is_empty() {
+        return;
+    }
+    for obj in objects.iter_mut() {
+        if obj.island.is_some_and(|id| woken_islands.contains(&id)) {
+            obj.wake();
+            obj.island = None;
+        }
+    }
+}
+
+/// Disjoint-set forest used for building the contact islands.
+#[derive(Debug)]
+struct UnionFind {
+    parents: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(size: usize) -> Self {
+        Self {
+            parents: (0..size).collect(),
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
+        }
+        i
+    }
+
+    fn union(&mut self, i: usize, j: usize) {
+        let i = self.find(i);
+        let j = self.find(j);
+        self.parents[i] = j;
+    }
+}
+
+/// Maps the items on the threads of the pool, or on the current thread
+/// without a pool, the results are in the same order as the items.
+fn parallel_map<T: Sync, U: Send>(
+    items: &[T],
+    thread_pool: Option<&ThreadPool>,
+    f: impl Fn(&T) -> U + Sync,
+) -> Vec<U> {
+    match thread_pool {
+        Some(pool) if items.len() >= MIN_ITEMS_PER_THREAD * 2 => {
+            pool.install(|| {
+                items
+                    .par_iter()
+                    .with_min_len(MIN_ITEMS_PER_THREAD)
+                    .map(&f)
+                    .collect()
+            })
+        }
+        _ => items.iter().map(f).collect(),
+    }
+}
+
+/// The part of an object that the narrowphase needs,
+/// unlike the object it can be shared between threads.
+#[derive(Debug, Clone)]
+struct Body {
+    position: Point3<f64>,
+    rotation: UnitQuaternion<f64>,
+    collider: Collider,
+}
+
+impl Body {
+    /// The spheres and boxes of the body at their world poses.
+    fn parts(&self) -> SmallVec<[Self; 1]> {
+        if !matches!(self.collider, Collider::Compound(_)) {
+            return smallvec::smallvec![self.clone()];
+        }
+        self.collider
+            .leaves()
+            .map(|(position, rotation, collider)| Self {
+                position: self.position + self.rotation * position.coords,
+                rotation: self.rotation * rotation,
+                collider,
+            })
+            .collect()
+    }
+
+    fn aabb(&self) -> AABB {
+        self.collider.aabb(&self.position, &self.rotation)
+    }
+}
+
+impl From<&Object> for Body {
+    fn from(obj: &Object) -> Self {
+        Self {
+            position: obj.position(),
+            rotation: obj.rotation(),
+            collider: obj.collider().clone(),
+        }
+    }
+}
+
+/// Mutably borrows two different elements, `i` has to be smaller than `j`.
+fn pair_mut<T>(slice: &mut [T], i: usize, j: usize) -> (&mut T, &mut T) {
+    assert!(i < j);
+    let (s1, s2) = slice.split_at_mut(j);
+    (&mut s1[i], &mut s2[0])
+}
+
+/// A contact manifold between two objects, every point shares the normal.
 #[derive(Debug)]
 struct Contact {
-    points: (Point3<f64>, Point3<f64>),
+    /// Points from the second object towards the first one.
     normal: Vector3<f64>,
+    points: SmallVec<[ContactPoint; MAX_MANIFOLD_POINTS]>,
+    material: ContactMaterial,
+    rolling_impulse: Vector3<f64>,
+}
+
+/// The combined material coefficients of the two objects in contact.
+#[derive(Debug, Default)]
+struct ContactMaterial {
+    restitution: f64,
+    static_friction: f64,
+    dynamic_friction: f64,
+    rolling_friction: f64,
+}
+
+#[derive(Debug)]
+struct ContactPoint {
+    points: (Point3<f64>, Point3<f64>),
+    depth: f64,
+    target_normal_velocity: f64,
+    normal_impulse: f64,
+    friction_impulse: Vector3<f64>,
+    pseudo_impulse: f64,
+}
+
+/// The impulses of a contact point at the end of a step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct CachedImpulse {
+    /// The contact point in the space of the first object.
+    pub(crate) point: Point3<f64>,
+    pub(crate) normal_impulse: f64,
+    pub(crate) friction_impulse: Vector3<f64>,
+}
+
+pub(crate) type CachedImpulses = SmallVec<[CachedImpulse; MAX_MANIFOLD_POINTS]>;
+
+/// Velocity used only for moving the objects out of each other,
+/// it is not added to the momentum.
+#[derive(Debug, Clone, Copy, Default)]
+struct PseudoVelocity {
+    linear: Vector3<f64>,
+    angular: Vector3<f64>,
+}
+
+impl PseudoVelocity {
+    fn at(&self, obj: &Object, point: Point3<f64>) -> Vector3<f64> {
+        self.linear + self.angular.cross(&(point - obj.position()))
+    }
+
+    fn apply_impulse(
+        &mut self,
+        obj: &Object,
+        attack_point: Point3<f64>,
+        impulse: Vector3<f64>,
+    ) {
+        if obj.is_dynamic() {
+            self.linear += impulse / obj.mass();
+            self.angular += obj.inverse_inertia()
+                * (attack_point - obj.position()).cross(&impulse);
+        }
+    }
+}
+
+impl Contact {
+    fn new(
+        points: (Point3<f64>, Point3<f64>),
+        normal: Vector3<f64>,
+        depth: f64,
+    ) -> Self {
+        Self::from_manifold(normal, &[ManifoldPoint { points, depth }])
+    }
+
+    fn from_manifold(normal: Vector3<f64>, manifold: &[ManifoldPoint]) -> Self {
+        Self {
+            normal,
+            points: manifold
+                .iter()
+                .map(|point| ContactPoint {
+                    points: point.points,
+                    depth: point.depth,
+                    target_normal_velocity: 0.0,
+                    normal_impulse: 0.0,
+                    friction_impulse: Vector3::zeros(),
+                    pseudo_impulse: 0.0,
+                })
+                .collect(),
+            material: ContactMaterial::default(),
+            rolling_impulse: Vector3::zeros(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{material::Material, object::ObjectBuilder};
+
+    #[test]
+    fn parallel_map_keeps_the_order() {
+        let items: Vec<usize> = (0..1000).collect();
+        let pool = ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        let serial = parallel_map(&items, None, |i| i * 2);
+        let parallel = parallel_map(&items, Some(&pool), |i| i * 2);
+        assert_eq!(serial, parallel);
+    }
+
+    #[test]
+    fn contacts_do_not_depend_on_the_threads() {
+        let mut world = World::<()>::new();
+        world.insert(floor());
+        // a grid of overlapping spheres resting on the floor
+        for x in 0..20 {
+            for z in 0..20 {
+                world.insert(
+                    ObjectBuilder::new(Collider::Sphere(0.6))
+                        .position(Point3::new(f64::from(x), 0.5, f64::from(z)))
+                        .build(),
+                );
+            }
+        }
+        let contacts = |threads: usize| {
+            let mut simulation = Simulation {
+                threads,
+                ..Simulation::default()
+            };
+            simulation
+                .check_contacts_rtree(world.objects())
+                .iter()
+                .map(|(i, j, contact)| {
+                    let points: Vec<_> =
+                        contact.points.iter().map(|p| p.points).collect();
+                    (*i, *j, contact.normal, points)
+                })
+                .collect::<Vec<_>>()
+        };
+        let serial = contacts(1);
+        assert!(serial.len() > MIN_ITEMS_PER_THREAD * 2);
+        assert_eq!(serial, contacts(4));
+    }
+
+    /// An immovable floor whose top is at zero.
+    fn floor() -> Object {
+        ObjectBuilder::new(Collider::Box(100.0, 1.0, 100.0))
+            .position(Point3::new(0.0, -0.5, 0.0))
+            .immovable()
+            .build()
+    }
+
+    #[test]
+    fn box_stack_stays_in_place() {
+        let mut world = World::<()>::new();
+        world.insert(floor());
+        let boxes: Vec<_> = (0..5)
+            .map(|i| {
+                world.insert(
+                    ObjectBuilder::new(Collider::Box(1.0, 1.0, 1.0))
+                        .position(Point3::new(0.0, f64::from(i) + 0.5, 0.0))
+                        .build(),
+                )
+            })
+            .collect();
+        let mut simulation = Simulation {
+            allow_sleeping: false,
+            ..Simulation::default()
+        };
+        simulate_steps(&mut simulation, &mut world, 300);
+        // every contact below a box can overlap a bit more than the slop
+        for (i, &handle) in (0..).zip(&boxes) {
+            let position = world[handle].position();
+            let sunk = f64::from(i) + 0.5 - position.y;
+            assert!(
+                (0.0..0.03 * f64::from(i + 1)).contains(&sunk),
+                "box {i} is at {position}"
+            );
+            assert!(
+                position.x.abs() < 0.02 && position.z.abs() < 0.02,
+                "box {i} is at {position}"
+            );
+        }
+    }
+
+    #[test]
+    fn slow_contacts_do_not_bounce() {
+        let bounce = |speed: f64| {
+            let mut world = World::<()>::new();
+            world.insert(floor());
+            let handle = world.insert(
+                ObjectBuilder::new(Collider::Box(1.0, 1.0, 1.0))
+                    .position(Point3::new(0.0, 0.5, 0.0))
+                    .velocity(Vector3::new(0.0, -speed, 0.0))
+                    .build(),
+            );
+            let mut simulation = Simulation {
+                gravity: Vector3::zeros(),
+                ..Simulation::default()
+            };
+            simulation.simulate(&mut world, 1.0 / 60.0);
+            world[handle].velocity().y
+        };
+        let threshold = Simulation::default().restitution_threshold;
+        assert!(bounce(threshold * 0.5).abs() < 1e-3);
+        assert!(bounce(threshold * 2.0) > threshold);
+    }
+
+    #[test]
+    fn ccd_sphere_bounces_off_a_thin_box() {
+        let bounce = |restitution: f64| {
+            let material = Material {
+                restitution,
+                ..Material::DEFAULT
+            };
+            let mut world = World::<()>::new();
+            world.insert(
+                ObjectBuilder::new(Collider::Box(0.05, 10.0, 10.0))
+                    .position(Point3::new(2.0, 0.0, 0.0))
+                    .material(material)
+                    .immovable()
+                    .build(),
+            );
+            let sphere = world.insert(
+                ObjectBuilder::new(Collider::Sphere(0.1))
+                    .velocity(Vector3::new(150.0, 0.0, 0.0))
+                    .material(material)
+                    .ccd()
+                    .build(),
+            );
+            let mut simulation = Simulation {
+                gravity: Vector3::zeros(),
+                ..Simulation::default()
+            };
+            simulation.simulate(&mut world, 1.0 / 60.0);
+            // the sphere stopped at the box instead of tunneling through
+            assert!(world[sphere].position().x < 2.0);
+            world[sphere].velocity()
+        };
+        let velocity = bounce(1.0);
+        assert!((velocity.x + 150.0).abs() < 1.0, "{velocity}");
+        assert!(velocity.yz().magnitude() < 1e-6, "{velocity}");
+        let velocity = bounce(0.0);
+        assert!(velocity.magnitude() < 1.0, "{velocity}");
+    }
+
+    #[test]
+    fn ice_slides_further_than_rubber() {
+        let slide = |material: Material| {
+            let rotation = UnitQuaternion::new(Vector3::z() * 0.3);
+            let mut world = World::<()>::new();
+            world.insert(
+                ObjectBuilder::new(Collider::Box(20.0, 1.0, 20.0))
+                    .rotation(rotation)
+                    .material(material)
+                    .immovable()
+                    .build(),
+            );
+            let start = rotation * Point3::new(0.0, 1.0, 0.0);
+            let handle = world.insert(
+                ObjectBuilder::new(Collider::Box(1.0, 1.0, 1.0))
+                    .position(start)
+                    .rotation(rotation)
+                    .material(material)
+                    .build(),
+            );
+            let mut simulation = Simulation::default();
+            simulate_steps(&mut simulation, &mut world, 60);
+            (world[handle].position() - start).magnitude()
+        };
+        let ice = slide(Material::ICE);
+        let rubber = slide(Material::RUBBER);
+        // tan(0.3) is between the friction of ice and rubber
+        assert!(rubber < 0.05, "{rubber}");
+        assert!(ice > 1.0, "{ice}");
+    }
+
+    /// Throws two spheres at each other and returns whether they
+    /// passed through each other without a contact.
+    fn spheres_pass_through(
+        mut simulation: Simulation,
+        (layer1, mask1): (u32, u32),
+        (layer2, mask2): (u32, u32),
+    ) -> bool {
+        let mut world = World::<()>::new();
+        let mut sphere = |x: f64, layer, mask| {
+            world.insert(
+                ObjectBuilder::new(Collider::Sphere(0.5))
+                    .position(Point3::new(x, 0.0, 0.0))
+                    .velocity(Vector3::new(-2.0 * x, 0.0, 0.0))
+                    .collision_layers(layer, mask)
+                    .build(),
+            )
+        };
+        let left = sphere(-2.0, layer1, mask1);
+        let right = sphere(2.0, layer2, mask2);
+        simulation.gravity = Vector3::zeros();
+        let mut touched = false;
+        for _ in 0..60 {
+            simulation.simulate(&mut world, 1.0 / 60.0);
+            touched |= !simulation.contact_events().is_empty();
+        }
+        let passed = world[left].position().x > 1.5
+            && world[right].position().x < -1.5
+            && (world[left].velocity().x - 4.0).abs() < 1e-9;
+        assert_eq!(passed, !touched);
+        passed
+    }
+
+    #[test]
+    fn disjoint_masks_do_not_collide() {
+        let all = (1, u32::MAX);
+        assert!(!spheres_pass_through(Simulation::default(), all, all));
+        assert!(spheres_pass_through(Simulation::default(), (1, 1), (2, 2)));
+        // both objects have to accept the other one
+        assert!(spheres_pass_through(
+            Simulation::default(),
+            (1, u32::MAX),
+            (2, 2)
+        ));
+    }
+
+    #[test]
+    fn collision_filter_rejects_pairs() {
+        let all = (1, u32::MAX);
+        let filtered = |layer: u32| Simulation {
+            collision_filter: Some(CollisionFilter::new(move |o1, o2| {
+                o1.collision_layer != layer && o2.collision_layer != layer
+            })),
+            ..Simulation::default()
+        };
+        assert!(spheres_pass_through(filtered(1), all, all));
+        assert!(!spheres_pass_through(filtered(2), all, all));
+    }
+
+    #[test]
+    fn body_falls_through_a_sensor() {
+        let fall = |with_sensor: bool| {
+            let mut world = World::<()>::new();
+            let sensor = with_sensor.then(|| {
+                world.insert(
+                    ObjectBuilder::new(Collider::Box(4.0, 1.0, 4.0))
+                        .immovable()
+                        .sensor()
+                        .build(),
+                )
+            });
+            let body = world.insert(
+                ObjectBuilder::new(Collider::Sphere(0.5))
+                    .position(Point3::new(0.0, 3.0, 0.0))
+                    .velocity(Vector3::new(0.0, -5.0, 0.0))
+                    .build(),
+            );
+            let mut simulation = Simulation::default();
+            let mut events = Vec::new();
+            for _ in 0..90 {
+                simulation.simulate(&mut world, 1.0 / 60.0);
+                events.extend_from_slice(simulation.sensor_events());
+            }
+            if let Some(sensor) = sensor {
+                let event = |kind| SensorEvent {
+                    kind,
+                    sensor,
+                    object: body,
+                };
+                assert_eq!(
+                    events,
+                    [
+                        event(SensorEventKind::Enter),
+                        event(SensorEventKind::Exit)
+                    ]
+                );
+                assert!(simulation.sensor_overlaps().is_empty());
+            }
+            let object = &world[body];
+            assert!(object.position().y < -3.0);
+            (
+                object.position(),
+                object.velocity(),
+                object.angular_velocity(),
+            )
+        };
+        // the sensor does not push the body
+        assert_eq!(fall(true), fall(false));
+    }
+
+    #[test]
+    fn gyroscopic_spin_stays_bounded() {
+        let mut world = World::<()>::new();
+        // spinning around the intermediate axis is unstable
+        let handle = world.insert(
+            ObjectBuilder::new(Collider::Box(3.0, 1.0, 0.5))
+                .angular_velocity(Vector3::new(0.01, 20.0, 0.01))
+                .build(),
+        );
+        let mut simulation = Simulation {
+            integrator: Integrator::ImplicitGyroscopic,
+            gravity: Vector3::zeros(),
+            ..Simulation::default()
+        };
+        let momentum_and_energy = |object: &Object| {
+            let angular_velocity = object.angular_velocity();
+            let inertia = object.inverse_inertia().try_inverse().unwrap();
+            let angular_momentum = inertia * angular_velocity;
+            (
+                angular_momentum,
+                0.5 * angular_velocity.dot(&angular_momentum),
+            )
+        };
+        let (initial_momentum, initial_energy) =
+            momentum_and_energy(&world[handle]);
+        for _ in 0..600 {
+            simulation.simulate(&mut world, 1.0 / 60.0);
+            let object = &world[handle];
+            let (momentum, energy) = momentum_and_energy(object);
+            assert!((momentum - initial_momentum).magnitude() < 1e-6);
+            // the implicit solve can only lose energy
+            assert!(energy <= initial_energy * (1.0 + 1e-6), "{energy}");
+            let norm = object.rotation().quaternion().norm();
+            assert!((norm - 1.0).abs() < 1e-9, "{norm}");
+        }
+        // it ends up spinning around the major axis
+        let (_, energy) = momentum_and_energy(&world[handle]);
+        assert!(energy > initial_energy * 0.9, "{energy}");
+    }
+
+    #[test]
+    fn free_fall_matches_the_analytic_solution() {
+        for integrator in [Integrator::VelocityVerlet, Integrator::RungeKutta4]
+        {
+            let mut world = World::<()>::new();
+            let handle = world.insert(
+                ObjectBuilder::new(Collider::Sphere(0.5))
+                    .velocity(Vector3::new(1.0, 2.0, 0.0))
+                    .build(),
+            );
+            let mut simulation = Simulation {
+                integrator,
+                ..Simulation::default()
+            };
+            simulate_steps(&mut simulation, &mut world, 60);
+            // both are exact for a constant force
+            let time = 1.0;
+            let velocity = Vector3::new(1.0, 2.0, 0.0);
+            let expected_position =
+                velocity * time + EARTH_GRAVITY * time * time / 2.0;
+            let expected_velocity = velocity + EARTH_GRAVITY * time;
+            let object = &world[handle];
+            assert!(
+                (object.position().coords - expected_position).magnitude()
+                    < 1e-9,
+                "{integrator:?} {}",
+                object.position()
+            );
+            assert!(
+                (object.velocity() - expected_velocity).magnitude() < 1e-9,
+                "{integrator:?} {}",
+                object.velocity()
+            );
+        }
+    }
+
+    /// A box resting on the floor at the given horizontal position,
+    /// with its bottom at the height.
+    fn resting_box(x: f64, bottom: f64) -> Object {
+        ObjectBuilder::new(Collider::Box(1.0, 1.0, 1.0))
+            .position(Point3::new(x, bottom + 0.5, 0.0))
+            .build()
+    }
+
+    fn simulate_steps(
+        simulation: &mut Simulation,
+        world: &mut World<()>,
+        steps: u32,
+    ) {
+        for _ in 0..steps {
+            simulation.simulate(world, 1.0 / 60.0);
+        }
+    }
+
+    #[test]
+    fn resting_body_falls_asleep() {
+        let mut world = World::<()>::new();
+        world.insert(floor());
+        let handle = world.insert(resting_box(0.0, 0.0));
+        let mut simulation = Simulation::default();
+        // half of the time to sleep
+        simulate_steps(&mut simulation, &mut world, 15);
+        assert!(!world[handle].sleeping);
+        simulate_steps(&mut simulation, &mut world, 30);
+        assert!(world[handle].sleeping);
+        assert!(world[handle].island.is_some());
+    }
+
+    #[test]
+    fn contact_events_begin_persist_and_end() {
+        let mut world = World::<()>::new();
+        world.insert(floor());
+        let handle = world.insert(resting_box(0.0, 0.0));
+        let called = Arc::new(Mutex::new(Vec::new()));
+        let mut simulation = Simulation {
+            allow_sleeping: false,
+            contact_callback: Some(ContactCallback::new({
+                let called = Arc::clone(&called);
+                move |event| called.lock().unwrap().push(event.kind)
+            })),
+            ..Simulation::default()
+        };
+        let mut kinds = Vec::new();
+        let mut step = |simulation: &mut Simulation, world: &mut World<()>| {
+            simulation.simulate(world, 1.0 / 60.0);
+            kinds.extend(simulation.contact_events().iter().map(|e| e.kind));
+        };
+        for _ in 0..10 {
+            step(&mut simulation, &mut world);
+        }
+        let position = world[handle].position();
+        world[handle].apply_impulse(position, Vector3::new(0.0, 10.0, 0.0));
+        for _ in 0..10 {
+            step(&mut simulation, &mut world);
+        }
+        let mut expected = vec![ContactEventKind::Begin];
+        expected.extend([ContactEventKind::Persist; 9]);
+        expected.push(ContactEventKind::End);
+        assert_eq!(kinds, expected);
+        assert_eq!(*called.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn sleeping_contacts_do_not_end() {
+        let mut world = World::<()>::new();
+        world.insert(floor());
+        let handle = world.insert(resting_box(0.0, 0.0));
+        let mut simulation = Simulation::default();
+        let mut kinds = Vec::new();
+        for _ in 0..60 {
+            simulation.simulate(&mut world, 1.0 / 60.0);
+            kinds.extend(simulation.contact_events().iter().map(|e| e.kind));
+        }
+        assert!(world[handle].sleeping);
+        assert_eq!(kinds[0], ContactEventKind::Begin);
+        assert!(kinds[1..].iter().all(|&k| k == ContactEventKind::Persist));
+        // waking up continues the contact instead of beginning it again
+        world[handle].wake();
+        simulation.simulate(&mut world, 1.0 / 60.0);
+        let kinds: Vec<_> =
+            simulation.contact_events().iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, [ContactEventKind::Persist]);
+    }
+
+    #[test]
+    fn waking_a_body_wakes_its_island() {
+        let mut world = World::<()>::new();
+        world.insert(floor());
+        let bottom = world.insert(resting_box(0.0, 0.0));
+        let top = world.insert(resting_box(0.0, 1.0));
+        let mut simulation = Simulation::default();
+        simulate_steps(&mut simulation, &mut world, 120);
+        assert!(world[bottom].sleeping && world[top].sleeping);
+
+        let position = world[top].position();
+        world[top].apply_impulse(position, Vector3::new(0.0, 1.0, 0.0));
+        simulation.simulate(&mut world, 1.0 / 60.0);
+        assert!(!world[bottom].sleeping && !world[top].sleeping);
+
+        // a new contact wakes the island too
+        simulate_steps(&mut simulation, &mut world, 120);
+        assert!(world[bottom].sleeping && world[top].sleeping);
+        world.insert(
+            ObjectBuilder::new(Collider::Sphere(0.5))
+                .position(Point3::new(0.0, 2.5, 0.0))
+                .velocity(Vector3::new(0.0, -3.0, 0.0))
+                .build(),
+        );
+        simulation.simulate(&mut world, 1.0 / 60.0);
+        assert!(!world[bottom].sleeping && !world[top].sleeping);
+    }
+
+    #[test]
+    fn immovable_floor_does_not_join_islands() {
+        let mut world = World::<()>::new();
+        world.insert(floor());
+        let left = world.insert(resting_box(-5.0, 0.0));
+        let right = world.insert(resting_box(5.0, 0.0));
+        let mut simulation = Simulation::default();
+        simulate_steps(&mut simulation, &mut world, 120);
+        assert!(world[left].sleeping && world[right].sleeping);
+        assert_ne!(world[left].island, world[right].island);
+
+        let position = world[left].position();
+        world[left].apply_impulse(position, Vector3::new(0.0, 1.0, 0.0));
+        simulation.simulate(&mut world, 1.0 / 60.0);
+        assert!(!world[left].sleeping);
+        assert!(world[right].sleeping);
+    }
+
+    #[test]
+    fn compound_contacts_are_merged_into_one_event() {
+        use crate::collider::CompoundChild;
+
+        let mut world = World::<()>::new();
+        world.insert(
+            ObjectBuilder::new(Collider::Box(100.0, 1.0, 100.0))
+                .position(Point3::new(0.0, -0.5, 0.0))
+                .immovable()
+                .build(),
+        );
+        let sphere = |x| {
+            CompoundChild::new(
+                Collider::Sphere(0.5),
+                Point3::new(x, 0.0, 0.0),
+                UnitQuaternion::identity(),
+            )
+        };
+        let dumbbell = Collider::compound([sphere(-1.0), sphere(1.0)]);
+        world.insert(
+            ObjectBuilder::new(dumbbell.unwrap())
+                .position(Point3::new(0.0, 0.45, 0.0))
+                .build(),
+        );
+        let mut simulation = Simulation::default();
+        simulation.simulate(&mut world, 1.0 / 60.0);
+        let events = simulation.contact_events();
+        assert_eq!(events.len(), 1);
+        let points = &events[0].points;
+        assert!(points.iter().any(|p| p.points.0.x < 0.0));
+        assert!(points.iter().any(|p| p.points.0.x > 0.0));
+    }
+
+    #[test]
+    fn runs_with_the_same_seed_are_the_same() {
+        use rand::Rng;
+
+        let run = || {
+            let mut simulation = Simulation::default();
+            simulation.reseed(42);
+            let mut world = World::<()>::new();
+            world.insert(
+                ObjectBuilder::new(Collider::Box(100.0, 1.0, 100.0))
+                    .immovable()
+                    .build(),
+            );
+            for i in 0..10 {
+                let offset = simulation.rng().gen_range(-0.5..0.5);
+                world.insert(
+                    ObjectBuilder::new(Collider::Box(1.0, 1.0, 1.0))
+                        .position(Point3::new(
+                            offset,
+                            1.2f64.mul_add(f64::from(i), 1.0),
+                            0.0,
+                        ))
+                        .build(),
+                );
+            }
+            for _ in 0..120 {
+                simulation.simulate(&mut world, 1.0 / 60.0);
+            }
+            world
+                .objects()
+                .iter()
+                .map(Object::position)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(), run());
+    }
 }
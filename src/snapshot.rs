@@ -0,0 +1,410 @@
+use std::io::{self, Read, Write};
+
+use nalgebra::{Point3, Quaternion, UnitQuaternion, Vector3};
+
+use crate::{
+    simulation::{CachedImpulse, CachedImpulses},
+    world::BodyHandle,
+};
+
+/// Identifies the checkpoint files.
+const MAGIC: &[u8; 8] = b"ONLABCKP";
+/// Increased whenever the layout of the checkpoint files changes.
+pub const CHECKPOINT_VERSION: u32 = 2;
+
+/// The state of a body that changes during the simulation,
+/// the shape and the material are not included.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BodyState {
+    pub(crate) handle: BodyHandle,
+    pub(crate) position: Point3<f64>,
+    pub(crate) rotation: UnitQuaternion<f64>,
+    pub(crate) momentum: Vector3<f64>,
+    pub(crate) angular_momentum: Vector3<f64>,
+    pub(crate) sleeping: bool,
+    pub(crate) sleep_timer: f64,
+    pub(crate) island: Option<u64>,
+    /// The time elapsed on the path of a kinematic body.
+    pub(crate) kinematic_time: Option<f64>,
+}
+
+/// The full state of a simulation and its bodies between two steps.
+///
+/// Restoring a snapshot with [`Simulation::restore`] continues the
+/// simulation exactly as it continued after the snapshot was taken.
+/// The world has to contain the same bodies as when the snapshot was
+/// taken, so a checkpoint file can be loaded after building the same
+/// scene again.
+///
+/// [`Simulation::restore`]: crate::simulation::Simulation::restore
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub(crate) bodies: Vec<BodyState>,
+    pub(crate) next_island_id: u64,
+    /// Ordered by the body pairs, so equal states have equal snapshots.
+    pub(crate) touching: Vec<((BodyHandle, BodyHandle), Vector3<f64>)>,
+    /// The impulses the contact solver continues from, ordered like
+    /// `touching`.
+    pub(crate) contact_impulses:
+        Vec<((BodyHandle, BodyHandle), CachedImpulses)>,
+    pub(crate) sensor_overlaps: Vec<(BodyHandle, BodyHandle)>,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    /// The data is not a checkpoint or it is corrupted.
+    InvalidFormat,
+    /// The checkpoint was written by a different version of the format.
+    UnsupportedVersion(u32),
+    /// The bodies of the world are not the bodies of the snapshot.
+    BodiesChanged,
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "checkpoint I/O error: {error}"),
+            Self::InvalidFormat => write!(f, "not a valid checkpoint"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "checkpoint version {version} is not supported, \
+                 expected {CHECKPOINT_VERSION}"
+            ),
+            Self::BodiesChanged => {
+                write!(f, "the world does not have the bodies of the snapshot")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::UnexpectedEof {
+            Self::InvalidFormat
+        } else {
+            Self::Io(error)
+        }
+    }
+}
+
+impl Snapshot {
+    /// The number of bodies in the snapshot.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.bodies.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.bodies.is_empty()
+    }
+
+    /// Writes the snapshot as a versioned little endian checkpoint.
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        let w = &mut writer;
+        w.write_all(MAGIC)?;
+        w.write_all(&CHECKPOINT_VERSION.to_le_bytes())?;
+        write_u64(w, self.next_island_id)?;
+        write_u64(w, self.bodies.len() as u64)?;
+        for body in &self.bodies {
+            write_handle(w, body.handle)?;
+            write_vector(w, &body.position.coords)?;
+            let rotation = body.rotation.quaternion();
+            write_vector(w, &rotation.imag())?;
+            write_f64(w, rotation.w)?;
+            write_vector(w, &body.momentum)?;
+            write_vector(w, &body.angular_momentum)?;
+            w.write_all(&[u8::from(body.sleeping)])?;
+            write_f64(w, body.sleep_timer)?;
+            write_option(w, body.island, write_u64)?;
+            write_option(w, body.kinematic_time, write_f64)?;
+        }
+        write_u64(w, self.touching.len() as u64)?;
+        for ((a, b), normal) in &self.touching {
+            write_handle(w, *a)?;
+            write_handle(w, *b)?;
+            write_vector(w, normal)?;
+        }
+        write_u64(w, self.contact_impulses.len() as u64)?;
+        for ((a, b), impulses) in &self.contact_impulses {
+            write_handle(w, *a)?;
+            write_handle(w, *b)?;
+            write_u64(w, impulses.len() as u64)?;
+            for impulse in impulses {
+                write_vector(w, &impulse.point.coords)?;
+                write_f64(w, impulse.normal_impulse)?;
+                write_vector(w, &impulse.friction_impulse)?;
+            }
+        }
+        write_u64(w, self.sensor_overlaps.len() as u64)?;
+        for (sensor, object) in &self.sensor_overlaps {
+            write_handle(w, *sensor)?;
+            write_handle(w, *object)?;
+        }
+        w.flush()
+    }
+
+    /// Reads a checkpoint written by [`Snapshot::write_to`].
+    pub fn read_from(mut reader: impl Read) -> Result<Self, SnapshotError> {
+        let r = &mut reader;
+        let mut magic = [0; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(SnapshotError::InvalidFormat);
+        }
+        let mut version = [0; 4];
+        r.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != CHECKPOINT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        let next_island_id = read_u64(r)?;
+        let body_count = read_len(r)?;
+        let mut bodies = Vec::new();
+        for _ in 0..body_count {
+            let handle = read_handle(r)?;
+            let position = Point3::from(read_vector(r)?);
+            let imag = read_vector(r)?;
+            let w = read_f64(r)?;
+            let quaternion = Quaternion::from_parts(w, imag);
+            // a valid rotation is kept bit for bit,
+            // normalizing it again would break the determinism
+            let rotation = UnitQuaternion::try_new(quaternion, f64::EPSILON)
+                .filter(|_| (quaternion.norm() - 1.0).abs() < 1e-6)
+                .map(|_| UnitQuaternion::new_unchecked(quaternion))
+                .ok_or(SnapshotError::InvalidFormat)?;
+            bodies.push(BodyState {
+                handle,
+                position,
+                rotation,
+                momentum: read_vector(r)?,
+                angular_momentum: read_vector(r)?,
+                sleeping: read_bool(r)?,
+                sleep_timer: read_f64(r)?,
+                island: read_option(r, read_u64)?,
+                kinematic_time: read_option(r, read_f64)?,
+            });
+        }
+        let touching_count = read_len(r)?;
+        let mut touching = Vec::new();
+        for _ in 0..touching_count {
+            let pair = (read_handle(r)?, read_handle(r)?);
+            touching.push((pair, read_vector(r)?));
+        }
+        let pair_count = read_len(r)?;
+        let mut contact_impulses = Vec::new();
+        for _ in 0..pair_count {
+            let pair = (read_handle(r)?, read_handle(r)?);
+            let point_count = read_len(r)?;
+            let mut impulses = CachedImpulses::new();
+            for _ in 0..point_count {
+                impulses.push(CachedImpulse {
+                    point: Point3::from(read_vector(r)?),
+                    normal_impulse: read_f64(r)?,
+                    friction_impulse: read_vector(r)?,
+                });
+            }
+            contact_impulses.push((pair, impulses));
+        }
+        let overlap_count = read_len(r)?;
+        let mut sensor_overlaps = Vec::new();
+        for _ in 0..overlap_count {
+            sensor_overlaps.push((read_handle(r)?, read_handle(r)?));
+        }
+        let mut rest = [0];
+        if r.read(&mut rest)? != 0 {
+            return Err(SnapshotError::InvalidFormat);
+        }
+        Ok(Self {
+            bodies,
+            next_island_id,
+            touching,
+            contact_impulses,
+            sensor_overlaps,
+        })
+    }
+}
+
+pub(crate) fn write_u64(w: &mut impl Write, value: u64) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_f64(w: &mut impl Write, value: f64) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_vector(w: &mut impl Write, vector: &Vector3<f64>) -> io::Result<()> {
+    vector.iter().try_for_each(|&value| write_f64(w, value))
+}
+
+pub(crate) fn write_handle(
+    w: &mut impl Write,
+    handle: BodyHandle,
+) -> io::Result<()> {
+    write_u64(w, handle.to_bits())
+}
+
+fn write_option<W: Write, V>(
+    w: &mut W,
+    value: Option<V>,
+    write: impl Fn(&mut W, V) -> io::Result<()>,
+) -> io::Result<()> {
+    w.write_all(&[u8::from(value.is_some())])?;
+    value.map_or(Ok(()), |value| write(w, value))
+}
+
+pub(crate) fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    r.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f64(r: &mut impl Read) -> io::Result<f64> {
+    let mut bytes = [0; 8];
+    r.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+fn read_bool(r: &mut impl Read) -> Result<bool, SnapshotError> {
+    let mut byte = [0];
+    r.read_exact(&mut byte)?;
+    match byte[0] {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(SnapshotError::InvalidFormat),
+    }
+}
+
+/// Reads a length, the data is invalid if it does not fit into memory.
+fn read_len(r: &mut impl Read) -> Result<usize, SnapshotError> {
+    usize::try_from(read_u64(r)?).map_err(|_| SnapshotError::InvalidFormat)
+}
+
+fn read_vector(r: &mut impl Read) -> io::Result<Vector3<f64>> {
+    Ok(Vector3::new(read_f64(r)?, read_f64(r)?, read_f64(r)?))
+}
+
+pub(crate) fn read_handle(r: &mut impl Read) -> io::Result<BodyHandle> {
+    read_u64(r).map(BodyHandle::from_bits)
+}
+
+fn read_option<R: Read, V>(
+    r: &mut R,
+    read: impl Fn(&mut R) -> io::Result<V>,
+) -> Result<Option<V>, SnapshotError> {
+    Ok(if read_bool(r)? { Some(read(r)?) } else { None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collider::Collider,
+        object::{Object, ObjectBuilder},
+        simulation::Simulation,
+        world::World,
+    };
+
+    /// Spheres falling on a huge sphere.
+    fn falling_spheres() -> (Simulation, World) {
+        let mut world = World::new();
+        world.insert(
+            ObjectBuilder::new(Collider::Sphere(100.0))
+                .position(Point3::new(0.0, -100.0, 0.0))
+                .immovable()
+                .build(),
+        );
+        for i in 0..5 {
+            world.insert(
+                ObjectBuilder::new(Collider::Sphere(0.5))
+                    .position(Point3::new(
+                        0.1 * f64::from(i),
+                        1.5f64.mul_add(f64::from(i), 1.0),
+                        0.0,
+                    ))
+                    .build(),
+            );
+        }
+        (Simulation::default(), world)
+    }
+
+    fn positions(world: &World) -> Vec<Point3<f64>> {
+        world.objects().iter().map(Object::position).collect()
+    }
+
+    #[test]
+    fn rollback_repeats_the_same_steps() {
+        let (mut simulation, mut world) = falling_spheres();
+        for _ in 0..30 {
+            simulation.simulate(&mut world, 1.0 / 60.0);
+        }
+        let snapshot = simulation.snapshot(&world);
+        for _ in 0..30 {
+            simulation.simulate(&mut world, 1.0 / 60.0);
+        }
+        let first_run = positions(&world);
+        simulation.restore(&mut world, &snapshot).unwrap();
+        for _ in 0..30 {
+            simulation.simulate(&mut world, 1.0 / 60.0);
+        }
+        assert_eq!(positions(&world), first_run);
+    }
+
+    #[test]
+    fn checkpoint_round_trip() {
+        let (mut simulation, mut world) = falling_spheres();
+        for _ in 0..60 {
+            simulation.simulate(&mut world, 1.0 / 60.0);
+        }
+        let snapshot = simulation.snapshot(&world);
+        let mut bytes = Vec::new();
+        snapshot.write_to(&mut bytes).unwrap();
+        assert_eq!(Snapshot::read_from(bytes.as_slice()).unwrap(), snapshot);
+        bytes.truncate(bytes.len() - 1);
+        assert!(matches!(
+            Snapshot::read_from(bytes.as_slice()),
+            Err(SnapshotError::InvalidFormat)
+        ));
+    }
+
+    #[test]
+    fn invalid_rotations_are_rejected() {
+        let (simulation, world) = falling_spheres();
+        for rotation in [
+            Quaternion::default(),
+            Quaternion::new(f64::NAN, 0.0, 0.0, 0.0),
+        ] {
+            let mut snapshot = simulation.snapshot(&world);
+            snapshot.bodies[1].rotation =
+                UnitQuaternion::new_unchecked(rotation);
+            let mut bytes = Vec::new();
+            snapshot.write_to(&mut bytes).unwrap();
+            assert!(matches!(
+                Snapshot::read_from(bytes.as_slice()),
+                Err(SnapshotError::InvalidFormat)
+            ));
+        }
+    }
+
+    #[test]
+    fn restoring_into_a_different_world_fails() {
+        let (mut simulation, mut world) = falling_spheres();
+        let snapshot = simulation.snapshot(&world);
+        let handle = world.handles()[1];
+        world.remove(handle);
+        assert!(matches!(
+            simulation.restore(&mut world, &snapshot),
+            Err(SnapshotError::BodiesChanged)
+        ));
+    }
+}
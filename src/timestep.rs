@@ -0,0 +1,85 @@
+/// Drives the simulation with a fixed step size independently of the
+/// frame rate, so the results do not depend on how fast the frames are
+/// rendered.
+///
+/// The time that is not simulated yet is kept in an accumulator, the
+/// objects can be drawn between their previous and current poses with
+/// [`FixedTimestep::alpha`].
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    /// The simulated time of one step.
+    pub step_size: f64,
+    /// The maximal number of steps made for a frame, the time that cannot
+    /// be caught up is dropped, so a slow frame does not slow down the
+    /// next ones.
+    pub max_steps: u32,
+    /// Multiplies the frame time, values below one cause slow motion.
+    pub time_scale: f64,
+    accumulator: f64,
+}
+
+impl FixedTimestep {
+    #[must_use]
+    pub const fn new(step_size: f64) -> Self {
+        Self {
+            step_size,
+            max_steps: 10,
+            time_scale: 1.0,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Adds the time of a frame to the accumulator,
+    /// and returns the number of steps that have to be simulated.
+    pub fn advance(&mut self, frame_delta: f64) -> u32 {
+        self.accumulator += frame_delta * self.time_scale;
+        let mut steps = 0;
+        while self.accumulator >= self.step_size && steps < self.max_steps {
+            self.accumulator -= self.step_size;
+            steps += 1;
+        }
+        if self.accumulator >= self.step_size {
+            self.accumulator %= self.step_size;
+        }
+        steps
+    }
+
+    /// How far the rendered frame is between the previous and the current
+    /// step, it is between zero and one.
+    #[must_use]
+    pub fn alpha(&self) -> f64 {
+        (self.accumulator / self.step_size).clamp(0.0, 1.0)
+    }
+
+    /// Throws away the time that is not simulated yet.
+    pub const fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(1.0 / 100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leftover_time_is_accumulated() {
+        let mut timestep = FixedTimestep::new(0.25);
+        assert_eq!(timestep.advance(0.625), 2);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-9);
+        assert_eq!(timestep.advance(0.125), 1);
+        assert!(timestep.alpha() < 1e-9);
+    }
+
+    #[test]
+    fn steps_are_limited() {
+        let mut timestep = FixedTimestep::new(0.01);
+        assert_eq!(timestep.advance(1.0), timestep.max_steps);
+        assert!(timestep.alpha() < 1.0);
+    }
+}
@@ -0,0 +1,288 @@
+use std::ops::{Index, IndexMut};
+
+use crate::{object::Object, ray::Ray};
+
+/// Identifies a body in a [`World`].
+///
+/// A handle stays valid while its body is in the world, the handles of
+/// removed bodies never refer to a body inserted later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BodyHandle {
+    slot: u32,
+    generation: u32,
+}
+
+impl BodyHandle {
+    /// Packs the handle into an integer for saving it.
+    pub(crate) const fn to_bits(self) -> u64 {
+        (self.generation as u64) << 32 | self.slot as u64
+    }
+
+    pub(crate) const fn from_bits(bits: u64) -> Self {
+        Self {
+            slot: bits as u32,
+            generation: (bits >> 32) as u32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    generation: u32,
+    /// The index of the body in the dense arrays, `None` if the slot is free.
+    index: Option<usize>,
+}
+
+/// The bodies of the simulation with a user data payload for each of them.
+///
+/// The bodies are stored densely, so the simulation can work on a slice of
+/// them. Removing a body moves the last body into its place, so the order
+/// of [`World::objects`] is not stable, only the handles are.
+#[derive(Debug)]
+pub struct World<T = ()> {
+    objects: Vec<Object>,
+    data: Vec<T>,
+    handles: Vec<BodyHandle>,
+    slots: Vec<Slot>,
+    free_slots: Vec<u32>,
+}
+
+impl<T> World<T> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            objects: Vec::new(),
+            data: Vec::new(),
+            handles: Vec::new(),
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+        }
+    }
+
+    /// Adds a body with the default user data.
+    pub fn insert(&mut self, object: Object) -> BodyHandle
+    where
+        T: Default,
+    {
+        self.insert_with_data(object, T::default())
+    }
+
+    pub fn insert_with_data(&mut self, object: Object, data: T) -> BodyHandle {
+        let index = self.objects.len();
+        let handle = if let Some(slot) = self.free_slots.pop() {
+            let entry = &mut self.slots[slot as usize];
+            entry.index = Some(index);
+            BodyHandle {
+                slot,
+                generation: entry.generation,
+            }
+        } else {
+            let slot = u32::try_from(self.slots.len())
+                .expect("a world cannot have more than u32::MAX slots");
+            self.slots.push(Slot {
+                generation: 0,
+                index: Some(index),
+            });
+            BodyHandle {
+                slot,
+                generation: 0,
+            }
+        };
+        self.objects.push(object);
+        self.data.push(data);
+        self.handles.push(handle);
+        handle
+    }
+
+    /// Removes the body and returns it with its user data,
+    /// `None` if it was already removed.
+    pub fn remove(&mut self, handle: BodyHandle) -> Option<(Object, T)> {
+        let index = self.index_of(handle)?;
+        let slot = &mut self.slots[handle.slot as usize];
+        slot.index = None;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(handle.slot);
+        let object = self.objects.swap_remove(index);
+        let data = self.data.swap_remove(index);
+        self.handles.swap_remove(index);
+        if let Some(moved) = self.handles.get(index) {
+            self.slots[moved.slot as usize].index = Some(index);
+        }
+        Some((object, data))
+    }
+
+    /// Removes every body, the old handles stay invalid.
+    pub fn clear(&mut self) {
+        for handle in std::mem::take(&mut self.handles) {
+            let slot = &mut self.slots[handle.slot as usize];
+            slot.index = None;
+            slot.generation = slot.generation.wrapping_add(1);
+            self.free_slots.push(handle.slot);
+        }
+        self.objects.clear();
+        self.data.clear();
+    }
+
+    #[must_use]
+    pub fn contains(&self, handle: BodyHandle) -> bool {
+        self.index_of(handle).is_some()
+    }
+
+    #[must_use]
+    pub fn get(&self, handle: BodyHandle) -> Option<&Object> {
+        self.index_of(handle).map(|i| &self.objects[i])
+    }
+
+    pub fn get_mut(&mut self, handle: BodyHandle) -> Option<&mut Object> {
+        self.index_of(handle).map(|i| &mut self.objects[i])
+    }
+
+    #[must_use]
+    pub fn data(&self, handle: BodyHandle) -> Option<&T> {
+        self.index_of(handle).map(|i| &self.data[i])
+    }
+
+    pub fn data_mut(&mut self, handle: BodyHandle) -> Option<&mut T> {
+        self.index_of(handle).map(|i| &mut self.data[i])
+    }
+
+    /// Mutably borrows two different bodies.
+    ///
+    /// # Panics
+    /// If the handles are the same or one of them was removed.
+    pub fn pair_mut(
+        &mut self,
+        a: BodyHandle,
+        b: BodyHandle,
+    ) -> (&mut Object, &mut Object) {
+        let i = self.index_of(a).expect("the body was removed");
+        let j = self.index_of(b).expect("the body was removed");
+        assert_ne!(i, j, "a body cannot be borrowed twice");
+        if i < j {
+            let (s1, s2) = self.objects.split_at_mut(j);
+            (&mut s1[i], &mut s2[0])
+        } else {
+            let (s1, s2) = self.objects.split_at_mut(i);
+            (&mut s2[0], &mut s1[j])
+        }
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (BodyHandle, &Object)> {
+        self.handles.iter().copied().zip(&self.objects)
+    }
+
+    pub fn iter_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (BodyHandle, &mut Object)> {
+        self.handles.iter().copied().zip(&mut self.objects)
+    }
+
+    /// The bodies in storage order, see [`World::handles`].
+    #[must_use]
+    pub fn objects(&self) -> &[Object] {
+        &self.objects
+    }
+
+    /// The bodies in storage order, see [`World::handles`].
+    pub fn objects_mut(&mut self) -> &mut [Object] {
+        &mut self.objects
+    }
+
+    /// The handles of the bodies in the same order as [`World::objects`].
+    #[must_use]
+    pub fn handles(&self) -> &[BodyHandle] {
+        &self.handles
+    }
+
+    /// The position of the body in [`World::objects`],
+    /// it changes when other bodies are removed.
+    #[must_use]
+    pub fn index_of(&self, handle: BodyHandle) -> Option<usize> {
+        self.slots
+            .get(handle.slot as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.index)
+    }
+
+    /// The first body hit by the ray and the distance along the ray
+    /// in units of the ray direction.
+    #[must_use]
+    pub fn cast_ray(&self, ray: &Ray) -> Option<(BodyHandle, f64)> {
+        self.iter()
+            .filter_map(|(handle, o)| {
+                o.collider()
+                    .check_ray_hit(o.position(), o.rotation(), ray)
+                    .map(|t| (handle, t))
+            })
+            .min_by(|(_, t1), (_, t2)| t1.total_cmp(t2))
+    }
+}
+
+impl<T> Default for World<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Index<BodyHandle> for World<T> {
+    type Output = Object;
+
+    fn index(&self, handle: BodyHandle) -> &Object {
+        self.get(handle).expect("the body was removed")
+    }
+}
+
+impl<T> IndexMut<BodyHandle> for World<T> {
+    fn index_mut(&mut self, handle: BodyHandle) -> &mut Object {
+        self.get_mut(handle).expect("the body was removed")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Point3;
+
+    use super::*;
+    use crate::{collider::Collider, object::ObjectBuilder};
+
+    fn sphere_at(x: f64) -> Object {
+        ObjectBuilder::new(Collider::Sphere(1.0))
+            .position(Point3::new(x, 0.0, 0.0))
+            .build()
+    }
+
+    #[test]
+    fn handles_survive_removing_other_bodies() {
+        let mut world = World::<&str>::new();
+        let a = world.insert_with_data(sphere_at(0.0), "a");
+        let b = world.insert_with_data(sphere_at(1.0), "b");
+        let c = world.insert_with_data(sphere_at(2.0), "c");
+        assert_eq!(world.remove(a).map(|(_, data)| data), Some("a"));
+        assert_eq!(world.len(), 2);
+        assert!((world[b].position().x - 1.0).abs() < 1e-9);
+        assert!((world[c].position().x - 2.0).abs() < 1e-9);
+        assert_eq!(world.data(c), Some(&"c"));
+    }
+
+    #[test]
+    fn stale_handles_are_rejected() {
+        let mut world = World::<()>::new();
+        let a = world.insert(sphere_at(0.0));
+        world.remove(a);
+        let b = world.insert(sphere_at(1.0));
+        assert_ne!(a, b);
+        assert!(world.get(a).is_none());
+        assert!(world.remove(a).is_none());
+        assert!(world.contains(b));
+    }
+}